@@ -0,0 +1,75 @@
+use std::{fmt::Write, path::PathBuf};
+
+use autobean::{ledger::Ledger, lints::find_double_entries};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const ACCOUNTS: [&str; 6] = [
+    "Assets:Bank:Checking",
+    "Assets:Bank:Savings",
+    "Liabilities:CreditCard",
+    "Expenses:Groceries",
+    "Expenses:Utilities:Power",
+    "Income:Salary",
+];
+
+/// Generate a ledger of `size` transactions spread across several years,
+/// where roughly every fiftieth transfer has been entered twice.
+fn generate_ledger(size: usize) -> Ledger {
+    let mut source = String::new();
+
+    for i in 0..size {
+        let day = i / 20;
+        let date = format!(
+            "{:04}-{:02}-{:02}",
+            2000 + day / 336,
+            (day / 28) % 12 + 1,
+            day % 28 + 1
+        );
+        let from = ACCOUNTS[i % ACCOUNTS.len()];
+        let to = ACCOUNTS[(i / ACCOUNTS.len() + 1 + i) % ACCOUNTS.len()];
+        let amount = (i * 7919) % 100_000;
+
+        writeln!(
+            source,
+            "{date} * \"Payee {i}\" \"\"\n  {from}  -{amount} DKK\n  {to}\n"
+        )
+        .unwrap();
+
+        if i % 50 == 0 {
+            writeln!(
+                source,
+                "{date} * \"Payee {i}\" \"\"\n  {to}  {amount} DKK\n  {from}\n"
+            )
+            .unwrap();
+        }
+    }
+
+    let directory = PathBuf::from("target/bench-ledgers").join(size.to_string());
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("generated.beancount"), source).unwrap();
+
+    Ledger::from_path(&directory).unwrap()
+}
+
+fn double_entries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_double_entries");
+    group.sample_size(10);
+
+    for size in [1_000, 10_000, 40_000] {
+        let ledger = generate_ledger(size);
+        let directives = ledger.directives();
+
+        for window in [0, 3] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("window={window}"), size),
+                &directives,
+                |b, directives| b.iter(|| find_double_entries(directives, window)),
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, double_entries);
+criterion_main!(benches);
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+
+use beancount_core::{Account, Transaction};
+use rust_decimal::Decimal;
+
+/// A posting whose amount is known, either because it was written out
+/// explicitly, or because it could be inferred from the other postings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedPosting<'a> {
+    pub account: &'a Account<'a>,
+    pub amount: Decimal,
+    pub currency: Option<&'a str>,
+}
+
+/// Resolve the amounts of all postings within the transaction, including a
+/// single elided posting, whose amount balances out the rest. Postings whose
+/// amount cannot be inferred are left out.
+pub fn resolved_postings<'a>(transaction: &'a Transaction<'a>) -> Vec<ResolvedPosting<'a>> {
+    let mut resolved: Vec<_> = transaction
+        .postings
+        .iter()
+        .filter_map(|posting| {
+            Some(ResolvedPosting {
+                account: &posting.account,
+                amount: posting.units.num?,
+                currency: posting.units.currency.as_deref(),
+            })
+        })
+        .collect();
+
+    let mut elided = transaction
+        .postings
+        .iter()
+        .filter(|posting| posting.units.num.is_none());
+
+    if let (Some(posting), None) = (elided.next(), elided.next()) {
+        let currencies: Vec<_> = resolved.iter().map(|p| p.currency).collect();
+
+        // The remaining amount is only well-defined if all other postings share a currency.
+        if let Some(currency) = currencies.first().copied() {
+            if currencies.iter().all(|c| *c == currency) {
+                let amount: Decimal = resolved.iter().map(|p| p.amount).sum();
+                resolved.push(ResolvedPosting {
+                    account: &posting.account,
+                    amount: -amount,
+                    currency: posting.units.currency.as_deref().or(currency),
+                });
+            }
+        }
+    }
+
+    resolved
+}
+
+/// The total amount moved by the transaction in each currency, that is the sum
+/// of all the positive postings.
+pub fn totals<'a>(transaction: &'a Transaction<'a>) -> BTreeMap<Option<&'a str>, Decimal> {
+    let mut totals = BTreeMap::new();
+
+    for posting in resolved_postings(transaction) {
+        if posting.amount.is_sign_positive() {
+            *totals.entry(posting.currency).or_default() += posting.amount;
+        }
+    }
+
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use beancount_core::Transaction;
+    use rust_decimal::Decimal;
+
+    use crate::{amount::resolved_postings, inline_ledger, ledger::Downcast};
+
+    #[test]
+    fn test_resolve_elided_posting() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 * "Example Payee" ""
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Groceries  1000 DKK
+            Expenses:Utilities:Power
+        "#
+        );
+
+        let transaction = ledger
+            .directives()
+            .iter()
+            .cloned()
+            .filter_map(Transaction::downcast)
+            .next()
+            .unwrap();
+
+        let amounts: Vec<_> = resolved_postings(&transaction)
+            .into_iter()
+            .map(|posting| (posting.amount, posting.currency))
+            .collect();
+
+        assert_eq!(
+            amounts,
+            vec![
+                (Decimal::from(-1500), Some("DKK")),
+                (Decimal::from(1000), Some("DKK")),
+                (Decimal::from(500), Some("DKK")),
+            ]
+        );
+    }
+}
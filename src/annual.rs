@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use beancount_core::Date;
+use beancount_core::{Date, Directive};
 use tabled::{
     settings::{object::Cell, Alignment},
     Table,
@@ -8,11 +8,15 @@ use tabled::{
 
 use crate::{
     balance::{self, Balance},
-    ledger::Ledger,
+    error::Error,
+    ledger::Sourced,
 };
 
-pub fn accounts(ledger: &Ledger, year: usize) -> (Table, Vec<PathBuf>) {
-    let items = balance::itemized_transactions(&ledger);
+pub fn accounts(
+    directives: &[Sourced<Directive>],
+    year: usize,
+) -> Result<(Table, Vec<PathBuf>), Error> {
+    let items = balance::itemized_transactions(directives)?;
 
     let mut statements = Vec::new();
 
@@ -52,15 +56,17 @@ pub fn accounts(ledger: &Ledger, year: usize) -> (Table, Vec<PathBuf>) {
             current_row += 1
         }
 
-        let statement = PathBuf::from(item.statement.as_ref());
+        if let Some(statement) = item.statement {
+            let statement = PathBuf::from(statement.as_ref());
 
-        table.push_record([
-            "Bilag".to_string(),
-            statement.file_name().unwrap().to_string_lossy().to_string(),
-            "".to_string(),
-        ]);
-        statements.push(statement);
-        current_row += 1;
+            table.push_record([
+                "Bilag".to_string(),
+                statement.file_name().unwrap().to_string_lossy().to_string(),
+                "".to_string(),
+            ]);
+            statements.push(statement);
+            current_row += 1;
+        }
 
         table.push_record(["", "", ""]);
         current_row += 1;
@@ -72,5 +78,5 @@ pub fn accounts(ledger: &Ledger, year: usize) -> (Table, Vec<PathBuf>) {
         table.modify(cell, Alignment::right());
     }
 
-    (table, statements)
+    Ok((table, statements))
 }
@@ -0,0 +1,154 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::Read,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+
+use crate::error::Error;
+
+/// Name of the cache file, relative to the ledger directory.
+pub const HASHES_FILE: &str = ".autobean-hashes";
+
+/// Modification time of a file, as seconds and nanoseconds since the unix epoch.
+type Modified = (u64, u32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CachedDigest {
+    modified: Modified,
+    digest: String,
+}
+
+/// Cache of SHA-256 digests of the documents referenced by `statement` clauses.
+///
+/// Entries are keyed on path and modification time, so only documents which
+/// have been added or touched since the last run have to be read from disk.
+#[derive(Debug, Default)]
+pub struct DocumentHashes {
+    entries: HashMap<PathBuf, CachedDigest>,
+    dirty: bool,
+}
+
+impl DocumentHashes {
+    /// Load the cache from `path`. A missing or malformed cache file is not
+    /// an error, it just means every document has to be hashed again.
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let Ok(contents) = std::fs::read_to_string(path.as_ref()) else {
+            debug!(
+                "no document hash cache found at {}",
+                path.as_ref().to_string_lossy()
+            );
+            return Self::default();
+        };
+
+        // Each line is formatted as: <digest>\t<seconds>\t<nanoseconds>\t<path>
+        let entries = contents
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(4, '\t');
+                let digest = fields.next()?.to_string();
+                let seconds = fields.next()?.parse().ok()?;
+                let nanoseconds = fields.next()?.parse().ok()?;
+                let path = PathBuf::from(fields.next()?);
+
+                Some((
+                    path,
+                    CachedDigest {
+                        modified: (seconds, nanoseconds),
+                        digest,
+                    },
+                ))
+            })
+            .collect();
+
+        DocumentHashes {
+            entries,
+            dirty: false,
+        }
+    }
+
+    /// Write the cache to `path`, if any digests were added or changed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut contents = String::new();
+        for (path, CachedDigest { modified, digest }) in entries {
+            writeln!(
+                contents,
+                "{digest}\t{}\t{}\t{}",
+                modified.0,
+                modified.1,
+                path.to_string_lossy()
+            )
+            .unwrap();
+        }
+
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Retrieve the hex-encoded SHA-256 digest of the document at `path`,
+    /// hashing it only if it isn't already cached for its current modification time.
+    pub fn digest(&mut self, path: &Path) -> Result<String, Error> {
+        let modified = std::fs::metadata(path)?
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| (duration.as_secs(), duration.subsec_nanos()))
+            .unwrap_or_default();
+
+        if let Some(cached) = self.entries.get(path) {
+            if cached.modified == modified {
+                return Ok(cached.digest.clone());
+            }
+        }
+
+        debug!("hashing document {}", path.to_string_lossy());
+        let mut file = std::fs::File::open(path)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+
+        let digest = format!("{:x}", hasher.finalize());
+        self.entries.insert(
+            path.to_path_buf(),
+            CachedDigest {
+                modified,
+                digest: digest.clone(),
+            },
+        );
+        self.dirty = true;
+
+        Ok(digest)
+    }
+
+    /// Like [`DocumentHashes::digest`], but logs and swallows errors, since
+    /// unreadable documents are reported by other lints.
+    pub fn try_digest(&mut self, path: &Path) -> Option<String> {
+        match self.digest(path) {
+            Ok(digest) => Some(digest),
+            Err(err) => {
+                warn!(
+                    "unable to hash document {}: {}",
+                    path.to_string_lossy(),
+                    err
+                );
+                None
+            }
+        }
+    }
+}
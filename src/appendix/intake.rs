@@ -0,0 +1,219 @@
+use std::path::{Path, PathBuf};
+
+use beancount_core::Directive;
+use chrono::NaiveDate;
+use log::debug;
+use rust_decimal::Decimal;
+
+use crate::{
+    appendix::{AppendixExtractor, IntoAppendices},
+    error::Error,
+    ledger::Sourced,
+};
+
+/// Find the next free id in the appendix sequence, which is one higher than
+/// the highest id currently in use.
+pub fn next_appendix_id<'a, Extractor: AppendixExtractor<'a>>(
+    directives: &[Sourced<'a, Directive<'a>>],
+) -> u64 {
+    directives
+        .iter()
+        .cloned()
+        .into_appendices::<Extractor>()
+        .iter()
+        .map(|entry| entry.appendix.id)
+        .max()
+        .unwrap_or(0)
+        + 1
+}
+
+/// Turn an arbitrary file name into something suitable for use in a statement
+/// path, e.g. `Receipt (Power Company).PDF` becomes `receipt-power-company`.
+pub fn slugify(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if slug.is_empty() {
+        String::from("document")
+    } else {
+        slug
+    }
+}
+
+/// A receipt or invoice being filed into the documents folder as a new appendix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    pub date: NaiveDate,
+    pub id: u64,
+    pub statement: PathBuf,
+}
+
+impl Attachment {
+    /// Determine where `receipt` should be filed within `documents`, named
+    /// `YYYY-MM-DD.<id>.<slug>.<ext>` as expected by
+    /// [`crate::appendix::statement::FromStatementPath`].
+    pub fn new(
+        receipt: &Path,
+        documents: &Path,
+        date: &str,
+        id: u64,
+        slug: Option<&str>,
+    ) -> Result<Self, Error> {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| Error::InvalidDate(date.to_string()))?;
+
+        let slug = slugify(slug.unwrap_or_else(|| {
+            receipt
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default()
+        }));
+
+        let filename = match receipt.extension() {
+            Some(extension) => format!(
+                "{date}.{id}.{slug}.{extension}",
+                extension = extension.to_string_lossy().to_lowercase()
+            ),
+            None => format!("{date}.{id}.{slug}"),
+        };
+
+        Ok(Attachment {
+            date,
+            id,
+            statement: documents.join(filename),
+        })
+    }
+
+    /// Move `receipt` into its place in the documents folder.
+    pub fn file(&self, receipt: &Path) -> Result<(), Error> {
+        if self.statement.exists() {
+            return Err(Error::DocumentExists(self.statement.clone()));
+        }
+
+        if let Some(parent) = self.statement.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        debug!(
+            "moving {} to {}",
+            receipt.to_string_lossy(),
+            self.statement.to_string_lossy()
+        );
+
+        // Renaming fails when moving across file systems, in which case we copy instead.
+        if std::fs::rename(receipt, &self.statement).is_err() {
+            std::fs::copy(receipt, &self.statement)?;
+            std::fs::remove_file(receipt)?;
+        }
+
+        Ok(())
+    }
+
+    /// Produce a transaction skeleton referring to this appendix, to be completed by hand.
+    pub fn skeleton(
+        &self,
+        payee: Option<&str>,
+        account: &str,
+        counter_account: &str,
+        amount: Option<(Decimal, &str)>,
+    ) -> String {
+        let amount = amount
+            .map(|(amount, currency)| format!("  {} {}", -amount, currency))
+            .unwrap_or_default();
+
+        // Quotes and backslashes would otherwise end the string early.
+        let payee = payee
+            .unwrap_or_default()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+
+        format!(
+            "{date} * \"{payee}\" \"\"\n    statement: \"{statement}\"\n    {account}{amount}\n    {counter_account}\n",
+            date = self.date,
+            statement = self.statement.to_string_lossy(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use rust_decimal::Decimal;
+
+    use crate::{
+        appendix::{
+            intake::{next_appendix_id, Attachment},
+            statement::FromStatementPath,
+        },
+        error::Error,
+        inline_ledger,
+    };
+
+    #[test]
+    fn test_next_appendix_id() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-02 * "Invoice" ""
+            statement: "documents/2000-01-02.7.invoice.pdf"
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Utilities:Power
+
+        2000-01-01 * "Invoice" ""
+            statement: "documents/2000-01-01.3.invoice.pdf"
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Utilities:Power
+        "#
+        );
+
+        let id = next_appendix_id::<FromStatementPath>(&ledger.directives());
+        assert_eq!(id, 8);
+
+        let attachment = Attachment::new(
+            Path::new("/tmp/Receipt (Power Company).PDF"),
+            Path::new("documents"),
+            "2000-01-03",
+            id,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            attachment.statement,
+            PathBuf::from("documents/2000-01-03.8.receipt-power-company.pdf")
+        );
+
+        assert_eq!(
+            attachment.skeleton(
+                Some("Power Company"),
+                "Assets:Bank:Account",
+                "Expenses:Utilities:Power",
+                Some((Decimal::from(1500), "DKK"))
+            ),
+            "2000-01-03 * \"Power Company\" \"\"\n    statement: \"documents/2000-01-03.8.receipt-power-company.pdf\"\n    Assets:Bank:Account  -1500 DKK\n    Expenses:Utilities:Power\n"
+        );
+
+        assert!(attachment
+            .skeleton(
+                Some(r#"The "Best" \ Shop"#),
+                "Assets:Bank:Account",
+                "Expenses:Utilities:Power",
+                None
+            )
+            .starts_with(r#"2000-01-03 * "The \"Best\" \\ Shop" """#));
+
+        // Dates have to exist, not merely look like one.
+        let invalid = Attachment::new(
+            Path::new("receipt.pdf"),
+            Path::new("documents"),
+            "2000-13-45",
+            id,
+            None,
+        );
+        assert!(matches!(invalid, Err(Error::InvalidDate(_))));
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    ops::RangeInclusive,
+    path::PathBuf,
+};
+
+use beancount_core::{Directive, Transaction};
+use colored::Colorize;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::{
+    amount::totals,
+    appendix::{Appendix, AppendixExtractor},
+    ledger::{Downcast, Sourced},
+    readable::account_name,
+};
+
+/// Criteria for narrowing down the listed appendices.
+#[derive(Debug, Default)]
+pub struct AppendixFilter {
+    /// Only consider transactions booked within this year.
+    pub year: Option<usize>,
+    /// Only consider transactions with postings to this account or its sub-accounts.
+    pub account: Option<String>,
+    /// Only list appendices whose documents do not exist.
+    pub missing_file: bool,
+    /// Only list appendices with ids in this range.
+    pub ids: Option<RangeInclusive<u64>>,
+}
+
+/// Parse an id range given as `3..10`, `3-10` or just `3`.
+pub fn parse_id_range(range: &str) -> Result<RangeInclusive<u64>, String> {
+    let (start, end) = range
+        .split_once("..")
+        .or_else(|| range.split_once('-'))
+        .unwrap_or((range, range));
+
+    let parse = |id: &str| {
+        id.trim()
+            .parse::<u64>()
+            .map_err(|_| format!("{id} is not a valid appendix id"))
+    };
+
+    Ok(parse(start)?..=parse(end)?)
+}
+
+impl AppendixFilter {
+    fn matches_transaction(&self, transaction: &Transaction) -> bool {
+        if let Some(year) = self.year {
+            if !transaction
+                .date
+                .to_string()
+                .starts_with(&format!("{year}-"))
+            {
+                return false;
+            }
+        }
+
+        if let Some(account) = &self.account {
+            if !transaction.postings.iter().any(|posting| {
+                let name = account_name(&posting.account);
+                name == *account || name.starts_with(&format!("{account}:"))
+            }) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn matches_appendix(&self, summary: &AppendixSummary) -> bool {
+        if self.missing_file && summary.exists {
+            return false;
+        }
+
+        if let Some(ids) = &self.ids {
+            if !ids.contains(&summary.id) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Total {
+    pub amount: String,
+    pub currency: Option<String>,
+}
+
+impl Display for Total {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.currency {
+            Some(currency) => write!(f, "{} {}", self.amount, currency),
+            None => write!(f, "{}", self.amount),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Reference {
+    pub file: String,
+    pub line: u32,
+    pub date: String,
+    pub payee: Option<String>,
+}
+
+/// An appendix along with the transactions referring to it.
+#[derive(Debug, Serialize)]
+pub struct AppendixSummary {
+    pub id: u64,
+    pub statement: String,
+    pub exists: bool,
+    pub payees: Vec<String>,
+    pub totals: Vec<Total>,
+    pub transactions: Vec<Reference>,
+}
+
+pub fn list_appendices<'a, Extractor: AppendixExtractor<'a>>(
+    directives: &[Sourced<'a, Directive<'a>>],
+    filter: &AppendixFilter,
+) -> Vec<AppendixSummary> {
+    let appendices: HashMap<Appendix, Vec<_>> = directives
+        .iter()
+        .cloned()
+        .filter_map(Transaction::downcast)
+        .filter(|transaction| filter.matches_transaction(transaction))
+        .filter_map(|transaction| {
+            Extractor::from_transaction(transaction.clone())
+                .ok()
+                .map(|appendix| (appendix, transaction))
+        })
+        .into_group_map();
+
+    let mut summaries: Vec<_> = appendices
+        .into_iter()
+        .map(|(Appendix { id, statement }, mut transactions)| {
+            transactions.sort_by(|a, b| a.location.cmp(&b.location));
+
+            let mut sums = BTreeMap::<Option<&str>, Decimal>::new();
+            for transaction in &transactions {
+                for (currency, amount) in totals(transaction) {
+                    *sums.entry(currency).or_default() += amount;
+                }
+            }
+
+            AppendixSummary {
+                id,
+                exists: PathBuf::from(&statement).exists(),
+                statement,
+                payees: transactions
+                    .iter()
+                    .filter_map(|transaction| transaction.payee.as_deref())
+                    .unique()
+                    .map(String::from)
+                    .collect(),
+                totals: sums
+                    .into_iter()
+                    .map(|(currency, amount)| Total {
+                        amount: amount.to_string(),
+                        currency: currency.map(String::from),
+                    })
+                    .collect(),
+                transactions: transactions
+                    .iter()
+                    .map(|transaction| Reference {
+                        file: transaction
+                            .location
+                            .ledger()
+                            .filename()
+                            .to_string_lossy()
+                            .to_string(),
+                        line: transaction.location.start() + 1,
+                        date: transaction.date.to_string(),
+                        payee: transaction.payee.as_deref().map(String::from),
+                    })
+                    .collect(),
+            }
+        })
+        .filter(|summary| filter.matches_appendix(summary))
+        .collect();
+
+    summaries.sort_by(|a, b| a.id.cmp(&b.id).then(a.statement.cmp(&b.statement)));
+    summaries
+}
+
+impl Display for AppendixSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let statement = if self.exists {
+            self.statement.bold().green()
+        } else {
+            self.statement.bold().red()
+        };
+
+        writeln!(f, "{id: >8} {statement}", id = self.id)?;
+        writeln!(
+            f,
+            "         {} {}",
+            self.totals.iter().join(", ").bold(),
+            self.payees.join(", ")
+        )?;
+
+        for reference in &self.transactions {
+            writeln!(f, "       ↳ {}:{}", reference.file, reference.line)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Render the summaries as csv, with one row per appendix.
+pub fn appendices_csv(summaries: &[AppendixSummary]) -> Result<String, csv::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "id",
+        "statement",
+        "exists",
+        "payees",
+        "totals",
+        "transactions",
+    ])?;
+
+    for summary in summaries {
+        writer.write_record([
+            summary.id.to_string(),
+            summary.statement.clone(),
+            summary.exists.to_string(),
+            summary.payees.join("; "),
+            summary.totals.iter().join("; "),
+            summary
+                .transactions
+                .iter()
+                .map(|reference| format!("{}:{}", reference.file, reference.line))
+                .join("; "),
+        ])?;
+    }
+
+    let bytes = writer.into_inner().map_err(|err| err.into_error())?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        appendix::{
+            listing::{list_appendices, parse_id_range, AppendixFilter},
+            statement::FromStatementPath,
+        },
+        inline_ledger,
+    };
+
+    #[test]
+    fn test_list_appendices() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 * "Power Company" ""
+            statement: "documents/2000-01-01.1.invoice.pdf"
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Utilities:Power
+
+        2000-01-02 * "Power Company" "Second installment"
+            statement: "documents/2000-01-01.1.invoice.pdf"
+            Assets:Bank:Account  -500 DKK
+            Expenses:Utilities:Power
+
+        2000-01-03 * "Grocery Store" ""
+            statement: "documents/2000-01-03.2.receipt.pdf"
+            Assets:Bank:Account  -200 DKK
+            Expenses:Groceries
+
+        2001-01-01 * "Power Company" ""
+            statement: "documents/2001-01-01.3.invoice.pdf"
+            Assets:Bank:Account  -1000 DKK
+            Expenses:Utilities:Power
+        "#
+        );
+
+        let directives = ledger.directives();
+        let filter = AppendixFilter {
+            year: Some(2000),
+            account: Some(String::from("Expenses:Utilities")),
+            ..Default::default()
+        };
+
+        let summaries = list_appendices::<FromStatementPath>(&directives, &filter);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].id, 1);
+        assert_eq!(summaries[0].transactions.len(), 2);
+        assert_eq!(summaries[0].payees, vec![String::from("Power Company")]);
+        assert_eq!(summaries[0].totals[0].to_string(), "2000 DKK");
+
+        let filter = AppendixFilter {
+            ids: Some(parse_id_range("2..3").unwrap()),
+            missing_file: true,
+            ..Default::default()
+        };
+
+        let summaries = list_appendices::<FromStatementPath>(&directives, &filter);
+        assert_eq!(summaries.len(), 2);
+    }
+}
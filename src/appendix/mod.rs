@@ -1,3 +1,7 @@
+pub mod hashes;
+pub mod intake;
+pub mod listing;
+pub mod renumber;
 pub mod statement;
 
 use beancount_core::{Directive, Transaction};
@@ -0,0 +1,332 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use beancount_core::Directive;
+use log::{debug, warn};
+
+use crate::{
+    appendix::{statement::FromStatementPath, IntoAppendices},
+    edit::FileEdit,
+    error::Error,
+    ledger::{LedgerFile, Sourced},
+};
+
+/// A statement whose appendix id changes as part of a renumbering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Renumbered {
+    pub from: String,
+    pub to: String,
+}
+
+/// The complete set of changes required to make the appendix ids of a
+/// ledger gap-free: the statements to rename, and the ledger files
+/// referring to them.
+#[derive(Debug)]
+pub struct Renumbering<'a> {
+    pub statements: Vec<Renumbered>,
+    pub edits: Vec<FileEdit<'a>>,
+}
+
+impl<'a> Renumbering<'a> {
+    /// Compute a gap-free sequence of appendix ids, starting at 1 and ordered by the
+    /// date of the earliest transaction referring to each appendix.
+    pub fn plan(directives: &[Sourced<'a, Directive<'a>>]) -> Self {
+        let appendices = directives
+            .iter()
+            .cloned()
+            .into_appendices::<FromStatementPath>();
+
+        // Many transactions can refer to the same appendix, in which case
+        // the earliest one determines its position in the sequence.
+        let mut earliest = HashMap::new();
+        for entry in &appendices {
+            let date = entry.transaction.date.to_string();
+            earliest
+                .entry(entry.appendix.clone())
+                .and_modify(|earliest: &mut String| {
+                    if date < *earliest {
+                        *earliest = date.clone()
+                    }
+                })
+                .or_insert(date);
+        }
+
+        let mut ordered: Vec<_> = earliest.into_iter().collect();
+        ordered.sort_by(|(a, a_date), (b, b_date)| a_date.cmp(b_date).then(a.cmp(b)));
+
+        let statements: Vec<_> = ordered
+            .into_iter()
+            .zip(1..)
+            .filter(|((appendix, _), id)| appendix.id != *id)
+            .filter_map(|((appendix, _), id)| {
+                Some(Renumbered {
+                    to: FromStatementPath::with_id(appendix.statement(), id)?,
+                    from: appendix.statement,
+                })
+            })
+            .collect();
+
+        let renamed: HashMap<&str, &str> = statements
+            .iter()
+            .map(|statement| (statement.from.as_str(), statement.to.as_str()))
+            .collect();
+
+        // Collect the lines to rewrite in each file, so each file is only edited once.
+        let mut lines: BTreeMap<PathBuf, (&'a LedgerFile, Vec<(usize, &str, &str)>)> =
+            BTreeMap::new();
+        for entry in &appendices {
+            let Some(to) = renamed.get(entry.appendix.statement()) else {
+                continue;
+            };
+
+            let location = &entry.transaction.location;
+            let (_, file_lines) = lines
+                .entry(location.ledger().filename())
+                .or_insert_with(|| (location.ledger(), Vec::new()));
+
+            for line in location.start()..location.end() {
+                file_lines.push((line as usize, entry.appendix.statement(), *to));
+            }
+        }
+
+        let edits = lines
+            .into_values()
+            .map(|(ledger, lines)| {
+                FileEdit::from_lines(ledger, |line_number, line| {
+                    let trimmed = line.trim_start();
+                    if !trimmed.starts_with("statement:") {
+                        return None;
+                    }
+
+                    lines
+                        .iter()
+                        .find(|(number, from, _)| {
+                            *number == line_number && line.contains(&format!("\"{from}\""))
+                        })
+                        .map(|(_, from, to)| {
+                            line.replacen(&format!("\"{from}\""), &format!("\"{to}\""), 1)
+                        })
+                })
+            })
+            .filter(|edit| !edit.is_empty())
+            .collect();
+
+        Renumbering { statements, edits }
+    }
+
+    /// Render the planned renames and file edits as a diff.
+    pub fn diff(&self) -> String {
+        let mut diff = String::new();
+
+        for Renumbered { from, to } in &self.statements {
+            diff.push_str(&format!("--- {from}\n+++ {to}\n"));
+        }
+
+        for edit in &self.edits {
+            diff.push_str(&edit.diff());
+        }
+
+        diff
+    }
+
+    /// Write the rewritten ledger files and rename the documents on disk.
+    ///
+    /// Nothing is touched if a document would overwrite another one, and if
+    /// anything fails partway, whatever was already done is undone again.
+    pub fn apply(&self) -> Result<(), Error> {
+        let mut moves = Vec::new();
+        for Renumbered { from, to } in &self.statements {
+            let from = PathBuf::from(from);
+            if !from.exists() {
+                warn!(
+                    "document {} does not exist, only its statement will be renumbered",
+                    from.to_string_lossy()
+                );
+                continue;
+            }
+
+            moves.push((from, PathBuf::from(to)));
+        }
+
+        // A new name may well be the old name of another document being moved,
+        // but must not be that of any other document.
+        let moved: HashSet<_> = moves.iter().map(|(from, _)| from).collect();
+        for (_, to) in &moves {
+            if to.exists() && !moved.contains(to) {
+                return Err(Error::DocumentExists(to.clone()));
+            }
+
+            if intermediate(to).exists() {
+                return Err(Error::DocumentExists(intermediate(to)));
+            }
+        }
+
+        let mut written = Vec::new();
+        let mut renamed = Vec::new();
+
+        let result = self.write_and_rename(&moves, &mut written, &mut renamed);
+        if result.is_err() {
+            for (from, to) in renamed.into_iter().rev() {
+                if let Err(err) = std::fs::rename(&to, &from) {
+                    warn!(
+                        "failed to move {} back to {}: {}",
+                        to.to_string_lossy(),
+                        from.to_string_lossy(),
+                        err
+                    );
+                }
+            }
+
+            for edit in written {
+                if let Err(err) = edit.revert() {
+                    warn!(
+                        "failed to restore {}: {}",
+                        edit.ledger.filename().to_string_lossy(),
+                        err
+                    );
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Apply the ledger edits and the renames, recording each step as it
+    /// succeeds so it can be undone.
+    fn write_and_rename<'b>(
+        &'b self,
+        moves: &[(PathBuf, PathBuf)],
+        written: &mut Vec<&'b FileEdit<'a>>,
+        renamed: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> Result<(), Error> {
+        for edit in &self.edits {
+            edit.apply()?;
+            written.push(edit);
+        }
+
+        // Rename in two steps, since a new name may well be the old name
+        // of another document which has yet to be moved.
+        let mut rename = |from: &Path, to: &Path| -> Result<(), Error> {
+            debug!(
+                "renaming {} to {}",
+                from.to_string_lossy(),
+                to.to_string_lossy()
+            );
+            std::fs::rename(from, to)?;
+            renamed.push((from.to_path_buf(), to.to_path_buf()));
+            Ok(())
+        };
+
+        for (from, to) in moves {
+            rename(from, &intermediate(to))?;
+        }
+
+        for (_, to) in moves {
+            rename(&intermediate(to), to)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Name a document is moved to while the documents are being renamed.
+fn intermediate(to: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.renumbering", to.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        appendix::renumber::{Renumbered, Renumbering},
+        error::Error,
+        inline_ledger,
+    };
+
+    #[test]
+    fn test_renumber_appendices() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-04 * "Invoice" ""
+            statement: "documents/2022-01-04.5.invoice.pdf"
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Utilities:Power
+
+        2000-01-03 * "Invoice" ""
+            statement:   "documents/2022-01-03.4.invoice.pdf"
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Utilities:Power
+
+        2000-01-02 * "Invoice" ""
+            statement: "documents/2022-01-02.2.invoice.pdf"
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Utilities:Power
+
+        2000-01-01 * "Invoice" ""
+            statement: "documents/2022-01-01.1.invoice.pdf"
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Utilities:Power
+        "#
+        );
+
+        let directives = ledger.directives();
+        let renumbering = Renumbering::plan(&directives);
+
+        assert_eq!(
+            renumbering.statements,
+            vec![
+                Renumbered {
+                    from: "documents/2022-01-03.4.invoice.pdf".to_string(),
+                    to: "documents/2022-01-03.3.invoice.pdf".to_string(),
+                },
+                Renumbered {
+                    from: "documents/2022-01-04.5.invoice.pdf".to_string(),
+                    to: "documents/2022-01-04.4.invoice.pdf".to_string(),
+                },
+            ]
+        );
+
+        assert_eq!(renumbering.edits.len(), 1);
+        let contents = &renumbering.edits[0].contents;
+        assert!(contents.contains("statement:   \"documents/2022-01-03.3.invoice.pdf\""));
+        assert!(contents.contains("statement: \"documents/2022-01-04.4.invoice.pdf\""));
+        assert!(!contents.contains(".5.invoice.pdf"));
+
+        println!("{}", renumbering.diff());
+    }
+
+    #[test]
+    fn test_renumber_collision() {
+        let root = std::path::PathBuf::from("target/test-renumber");
+        std::fs::remove_dir_all(&root).ok();
+        std::fs::create_dir_all(&root).unwrap();
+
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 * "Invoice" ""
+            statement: "target/test-renumber/2000-01-01.2.invoice.pdf"
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Utilities:Power
+        "#
+        );
+
+        // An unrelated document already has the new name.
+        std::fs::write(root.join("2000-01-01.2.invoice.pdf"), "invoice").unwrap();
+        std::fs::write(root.join("2000-01-01.1.invoice.pdf"), "unrelated").unwrap();
+
+        let directives = ledger.directives();
+        let renumbering = Renumbering::plan(&directives);
+        assert_eq!(renumbering.statements.len(), 1);
+
+        assert!(matches!(
+            renumbering.apply(),
+            Err(Error::DocumentExists(path)) if path == root.join("2000-01-01.1.invoice.pdf")
+        ));
+        assert_eq!(
+            std::fs::read_to_string(root.join("2000-01-01.1.invoice.pdf")).unwrap(),
+            "unrelated"
+        );
+        assert!(root.join("2000-01-01.2.invoice.pdf").exists());
+    }
+}
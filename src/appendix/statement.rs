@@ -13,6 +13,27 @@ pub struct FromStatementPath;
 static DATE_DOT_ID: Lazy<Regex> =
     Lazy::new(|| Regex::new(".*/?\\d\\d\\d\\d\\-\\d\\d\\-\\d\\d\\.(\\d+)\\..*").unwrap());
 
+impl FromStatementPath {
+    /// The appendix id within `statement`, if it follows the naming scheme.
+    pub fn id(statement: &str) -> Option<u64> {
+        DATE_DOT_ID
+            .captures(statement)?
+            .get(1)?
+            .as_str()
+            .parse()
+            .ok()
+    }
+
+    /// Produce a copy of `statement` with its appendix id replaced by `id`.
+    pub fn with_id(statement: &str, id: u64) -> Option<String> {
+        let range = DATE_DOT_ID.captures(statement)?.get(1)?.range();
+
+        let mut renumbered = statement.to_string();
+        renumbered.replace_range(range, &id.to_string());
+        Some(renumbered)
+    }
+}
+
 impl<'a> AppendixExtractor<'a> for FromStatementPath {
     fn from_transaction(
         transaction: Sourced<'a, Transaction<'a>>,
@@ -73,7 +94,8 @@ mod tests {
 
         let appendix = ledger
             .directives()
-            .into_iter()
+            .iter()
+            .cloned()
             .filter_map(Transaction::downcast)
             .map(FromStatementPath::from_transaction)
             .next()
@@ -102,7 +124,8 @@ mod tests {
 
         let appendix = ledger
             .directives()
-            .into_iter()
+            .iter()
+            .cloned()
             .filter_map(Transaction::downcast)
             .map(FromStatementPath::from_transaction)
             .next()
@@ -129,7 +152,8 @@ mod tests {
 
         let appendix = ledger
             .directives()
-            .into_iter()
+            .iter()
+            .cloned()
             .filter_map(Transaction::downcast)
             .map(FromStatementPath::from_transaction)
             .next()
@@ -7,7 +7,7 @@ use tabled::{
     Table, Tabled,
 };
 
-use crate::ledger::Ledger;
+use crate::{error::Error, ledger::Sourced, readable::account_name};
 
 #[derive(Debug)]
 pub struct Transfer<'a> {
@@ -18,7 +18,7 @@ pub struct Transfer<'a> {
 #[derive(Debug)]
 pub struct Item<'a> {
     pub date: Date<'a>,
-    pub statement: Cow<'a, str>,
+    pub statement: Option<Cow<'a, str>>,
     pub payee: Option<Cow<'a, str>>,
     pub description: Cow<'a, str>,
     pub postings: Vec<Transfer<'a>>,
@@ -38,17 +38,19 @@ fn sums_for<'a>(items: impl Iterator<Item = &'a Item<'a>>) -> HashMap<Account<'a
     sums
 }
 
-pub fn itemized_transactions(ledger: &Ledger) -> Vec<Item> {
+/// Transactions with their postings converted to DKK. Postings in other
+/// currencies must have a price attached.
+pub fn itemized_transactions<'a>(
+    directives: &[Sourced<'a, Directive<'a>>],
+) -> Result<Vec<Item<'a>>, Error> {
     let mut items = Vec::<Item>::default();
 
-    for directive in ledger.directives() {
-        match directive.inner {
+    for directive in directives {
+        match directive.inner.clone() {
             Directive::Transaction(mut txn) => {
-                let Some(MetaValue::Text(statement)) = txn.meta.remove("statement") else {
-                    panic!(
-                        "Transaction {:#?} does not contain a statement!",
-                        directive.location
-                    );
+                let statement = match txn.meta.remove("statement") {
+                    Some(MetaValue::Text(statement)) => Some(statement),
+                    _ => None,
                 };
 
                 let mut item = Item {
@@ -66,19 +68,19 @@ pub fn itemized_transactions(ledger: &Ledger) -> Vec<Item> {
                     let Some(posting) = postings.pop() else { break };
 
                     if let Some(num) = posting.units.num {
-                        let num = if posting
-                            .units
-                            .currency
-                            .as_ref()
-                            .is_some_and(|currency| currency != "DKK")
-                        {
-                            if let Some(price) = posting.price.and_then(|price| price.num) {
-                                num * price
-                            } else {
-                                panic!("No price attached!")
+                        let num = match posting.units.currency.as_deref() {
+                            Some(currency) if currency != "DKK" => {
+                                match posting.price.and_then(|price| price.num) {
+                                    Some(price) => num * price,
+                                    None => {
+                                        return Err(Error::MissingPrice(format!(
+                                            "{} {} on {}",
+                                            num, currency, item.date
+                                        )))
+                                    }
+                                }
                             }
-                        } else {
-                            num
+                            _ => num,
                         };
 
                         item.postings.push(Transfer {
@@ -106,11 +108,14 @@ pub fn itemized_transactions(ledger: &Ledger) -> Vec<Item> {
     }
 
     items.sort_by_key(|item| item.date.to_string());
-    items
+    Ok(items)
 }
 
-pub fn balance(ledger: &Ledger, up_to_and_including: Option<usize>) -> Table {
-    let items = itemized_transactions(&ledger);
+pub fn balance(
+    directives: &[Sourced<Directive>],
+    up_to_and_including: Option<usize>,
+) -> Result<Table, Error> {
+    let items = itemized_transactions(directives)?;
 
     let balance = if let Some(year) = up_to_and_including {
         let end_date = Date::from_string_unchecked(format!("{year}-01-01", year = year + 1));
@@ -119,7 +124,7 @@ pub fn balance(ledger: &Ledger, up_to_and_including: Option<usize>) -> Table {
         sums_for(items.iter())
     };
 
-    tabled_balance(balance.iter())
+    Ok(tabled_balance(balance.iter()))
 }
 
 fn tabled_balance<'a>(balances: impl Iterator<Item = (&'a Account<'a>, &'a Decimal)>) -> Table {
@@ -142,7 +147,7 @@ pub struct Balance<'a>(pub (&'a Account<'a>, &'a Decimal));
 
 impl<'a> Balance<'a> {
     pub fn name(&self) -> String {
-        format!("{:?}:{}", self.0 .0.ty, self.0 .0.parts.join(":"),)
+        account_name(self.0 .0)
     }
 
     pub fn balance(&self) -> Decimal {
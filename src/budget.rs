@@ -0,0 +1,312 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use beancount_core::{Custom, Directive};
+use chrono::{Datelike, Duration, NaiveDate};
+use log::warn;
+use rust_decimal::Decimal;
+use tabled::{
+    builder::Builder,
+    settings::{object::Columns, Alignment},
+    Table,
+};
+
+use crate::{
+    amount::resolved_postings,
+    date::naive_date,
+    ledger::Sourced,
+    location::Location,
+    readable::account_name,
+    trend::{within, Period},
+};
+
+/// Length of the period a budget is given for, named as in fava.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interval {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Interval {
+    fn parse(interval: &str) -> Option<Self> {
+        match interval {
+            "daily" => Some(Interval::Daily),
+            "weekly" => Some(Interval::Weekly),
+            "monthly" => Some(Interval::Monthly),
+            "quarterly" => Some(Interval::Quarterly),
+            "yearly" => Some(Interval::Yearly),
+            _ => None,
+        }
+    }
+
+    /// Dates within the interval containing `date`. Weeks start on Mondays.
+    pub fn range(&self, date: NaiveDate) -> Range<NaiveDate> {
+        match self {
+            Interval::Daily => date..date + Duration::days(1),
+            Interval::Weekly => {
+                let start = date - Duration::days(date.weekday().num_days_from_monday().into());
+                start..start + Duration::days(7)
+            }
+            Interval::Monthly => Period::Month.range(date),
+            Interval::Quarterly => Period::Quarter.range(date),
+            Interval::Yearly => Period::Year.range(date),
+        }
+    }
+}
+
+/// A `custom "budget" Expenses:Food "monthly" 3000 DKK` directive, which is
+/// in effect from its date until the next budget of the same account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Budget<'a> {
+    pub date: NaiveDate,
+    pub account: String,
+    pub interval: Interval,
+    pub amount: Decimal,
+    pub currency: Option<String>,
+    pub location: Location<'a>,
+}
+
+fn unquote(arg: &str) -> &str {
+    arg.trim().trim_matches('"')
+}
+
+impl<'a> Budget<'a> {
+    fn from_custom(custom: &Custom, location: &Location<'a>) -> Option<Self> {
+        let args: Vec<_> = custom.args.iter().map(|arg| unquote(arg)).collect();
+        let [account, interval, amount @ ..] = args.as_slice() else {
+            return None;
+        };
+
+        let mut amount = amount.iter().flat_map(|arg| arg.split_whitespace());
+
+        Some(Budget {
+            date: naive_date(&custom.date)?,
+            account: account.to_string(),
+            interval: Interval::parse(interval)?,
+            amount: amount.next()?.parse().ok()?,
+            currency: amount.next().map(str::to_string),
+            location: location.clone(),
+        })
+    }
+
+    /// Share of the budget falling on `day`.
+    fn daily(&self, day: NaiveDate) -> Decimal {
+        let range = self.interval.range(day);
+        self.amount / Decimal::from((range.end - range.start).num_days())
+    }
+}
+
+/// Budgets defined by the ledger, in date order.
+pub fn budgets<'a>(directives: &[Sourced<'a, Directive<'a>>]) -> Vec<Budget<'a>> {
+    let mut budgets: Vec<_> = directives
+        .iter()
+        .filter_map(|directive| match &directive.inner {
+            Directive::Custom(custom) if unquote(&custom.name) == "budget" => {
+                let budget = Budget::from_custom(custom, &directive.location);
+                if budget.is_none() {
+                    warn!("ignoring malformed budget:\n{}", directive.location);
+                }
+                budget
+            }
+            _ => None,
+        })
+        .collect();
+
+    budgets.sort_by_key(|budget| budget.date);
+    budgets
+}
+
+/// Budgets of the same account and currency, in date order.
+pub(crate) fn by_account<'b, 'a>(
+    budgets: &'b [Budget<'a>],
+) -> BTreeMap<(&'b str, Option<&'b str>), Vec<&'b Budget<'a>>> {
+    let mut accounts = BTreeMap::<_, Vec<_>>::new();
+    for budget in budgets {
+        accounts
+            .entry((budget.account.as_str(), budget.currency.as_deref()))
+            .or_default()
+            .push(budget);
+    }
+    accounts
+}
+
+/// Amount budgeted within `range`, where each day counts towards the budget
+/// in effect on that day.
+pub(crate) fn budgeted(budgets: &[&Budget], range: Range<NaiveDate>) -> Decimal {
+    range
+        .start
+        .iter_days()
+        .take_while(|day| *day < range.end)
+        .filter_map(|day| {
+            let budget = budgets.iter().rev().find(|budget| budget.date <= day)?;
+            Some(budget.daily(day))
+        })
+        .sum::<Decimal>()
+        .round_dp(2)
+}
+
+/// Amount posted to `account` and its subaccounts within `range`.
+pub(crate) fn spent(
+    directives: &[Sourced<Directive>],
+    account: &str,
+    currency: Option<&str>,
+    range: Range<NaiveDate>,
+) -> Decimal {
+    directives
+        .iter()
+        .filter_map(|directive| match &directive.inner {
+            Directive::Transaction(txn) => Some((naive_date(&txn.date)?, txn)),
+            _ => None,
+        })
+        .filter(|(date, _)| range.contains(date))
+        .flat_map(|(_, txn)| resolved_postings(txn))
+        .filter(|posting| within(&account_name(posting.account), account))
+        .filter(|posting| {
+            currency.is_none() || posting.currency.is_none() || posting.currency == currency
+        })
+        .map(|posting| posting.amount)
+        .sum()
+}
+
+/// Budget against actual spending of an account within a period.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variance {
+    pub account: String,
+    pub period: String,
+    pub budget: Decimal,
+    pub actual: Decimal,
+    pub currency: Option<String>,
+}
+
+impl Variance {
+    /// Amount left of the budget, negative if it has been exceeded.
+    pub fn variance(&self) -> Decimal {
+        self.budget - self.actual
+    }
+}
+
+/// Compare the budget of each account with its postings, for every period
+/// from the one containing `from` to the one containing `to`.
+pub fn report(
+    directives: &[Sourced<Directive>],
+    budgets: &[Budget],
+    period: Period,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<Variance> {
+    let mut periods = Vec::new();
+    let mut range = period.range(from);
+    while range.start <= to {
+        let next = period.range(range.end);
+        periods.push(range);
+        range = next;
+    }
+
+    let mut variances = Vec::new();
+
+    for ((account, currency), budgets) in by_account(budgets) {
+        for range in &periods {
+            let budget = budgeted(&budgets, range.clone());
+            let actual = spent(directives, account, currency, range.clone());
+
+            if budget.is_zero() && actual.is_zero() {
+                continue;
+            }
+
+            variances.push(Variance {
+                account: account.to_string(),
+                period: period.label(range.start),
+                budget,
+                actual,
+                currency: currency.map(str::to_string),
+            });
+        }
+    }
+
+    variances
+}
+
+pub fn table(variances: &[Variance]) -> Table {
+    let mut builder = Builder::new();
+    builder.push_record(["Account", "Period", "Budget", "Actual", "Variance"]);
+
+    for variance in variances {
+        let currency = variance.currency.as_deref().unwrap_or_default();
+        builder.push_record([
+            variance.account.clone(),
+            variance.period.clone(),
+            format!("{} {}", variance.budget, currency),
+            format!("{} {}", variance.actual, currency),
+            format!("{} {}", variance.variance(), currency),
+        ]);
+    }
+
+    let mut table = builder.build();
+    table.modify(Columns::new(2..), Alignment::right());
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::{budgets, report, Interval};
+    use crate::{inline_ledger, trend::Period};
+
+    #[test]
+    fn test_budget_report() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 custom "budget" Expenses:Food "monthly" 3000 DKK
+
+        2000-01-01 custom "budget" Expenses:Travel "yearly" 1200 DKK
+
+        2000-01-02 * "Netto" ""
+            Assets:Bank:Account  -3500 DKK
+            Expenses:Food:Groceries
+
+        2000-02-03 * "DSB" ""
+            Assets:Bank:Account  -400 DKK
+            Expenses:Travel
+
+        2000-03-01 custom "budget" Expenses:Food "weekly" 700 DKK
+        "#
+        );
+
+        let directives = ledger.directives();
+        let budgets = budgets(&directives);
+        assert_eq!(budgets.len(), 3);
+        assert_eq!(budgets[0].account, "Expenses:Food");
+        assert_eq!(budgets[0].currency.as_deref(), Some("DKK"));
+        assert_eq!(budgets[2].interval, Interval::Weekly);
+
+        let date = |month, day| NaiveDate::from_ymd_opt(2000, month, day).unwrap();
+        let variances = report(
+            &directives,
+            &budgets,
+            Period::Month,
+            date(1, 15),
+            date(3, 1),
+        );
+        println!("{}", super::table(&variances));
+
+        let food: Vec<_> = variances
+            .iter()
+            .filter(|variance| variance.account == "Expenses:Food")
+            .collect();
+        assert_eq!(food.len(), 3);
+        assert_eq!(food[0].period, "2000-01");
+        assert_eq!(food[0].variance(), Decimal::new(-500, 0));
+        assert_eq!(food[2].budget, Decimal::new(3100, 0));
+
+        let travel = variances
+            .iter()
+            .find(|variance| variance.account == "Expenses:Travel" && variance.period == "2000-02")
+            .unwrap();
+        assert_eq!(travel.budget, Decimal::new(9508, 2));
+        assert_eq!(travel.actual, Decimal::new(400, 0));
+    }
+}
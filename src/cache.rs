@@ -0,0 +1,345 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use beancount_core::{metadata::MetaValue, Directive};
+use chrono::NaiveDate;
+use colored::Colorize;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    appendix::hashes::DocumentHashes,
+    error::Error,
+    ledger::{Ledger, Sourced},
+    lints::{self, Lint, LintOptions, FILE_LOCAL_KINDS},
+    location::{Location, ToLocationSpan},
+};
+
+/// Name of the cache directory, relative to the ledger directory.
+pub const CACHE_DIR: &str = ".autobean-cache";
+
+const CACHE_FILE: &str = "lints.json";
+
+/// A finding of the lints, kept apart from its rendering so colours follow
+/// the terminal it is eventually printed to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    kind: String,
+    summary: String,
+    /// File and zero-indexed, half-open range of each of the lines referred to.
+    locations: Vec<(PathBuf, u32, u32)>,
+}
+
+impl Finding {
+    fn from(lint: &Lint) -> Self {
+        Finding {
+            kind: lint.kind().to_string(),
+            summary: lint.summary(),
+            locations: lint
+                .locations()
+                .iter()
+                .map(|location| {
+                    (
+                        location.ledger().filename(),
+                        location.start(),
+                        location.end(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Render the finding along with the lines it refers to in `ledger`.
+    pub fn render(&self, ledger: &Ledger) -> String {
+        let mut rendered = format!("{} {}:\n", "warning:".yellow().bold(), self.summary);
+
+        let locations = self.locations.iter().filter_map(|(path, start, end)| {
+            let file = ledger.files().find(|file| file.filename() == *path)?;
+            Some(Location::from(file, *start, *end))
+        });
+
+        for source in locations.to_span(10) {
+            let _ = writeln!(rendered, "{}", source);
+        }
+
+        rendered
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CachedFile {
+    /// SHA-256 digest of the contents of the file.
+    digest: String,
+    /// Documents referred to by the `statement` clauses of the file.
+    statements: Vec<String>,
+    /// Findings of the file-local lints.
+    findings: Vec<Finding>,
+}
+
+/// Findings of a previous run of the lints, keyed on the contents of the files
+/// they were found in.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LintCache {
+    version: String,
+    options: String,
+    files: BTreeMap<PathBuf, CachedFile>,
+    /// Digest of everything the cross-file lints depend on.
+    cross_file_digest: String,
+    cross_file_findings: Vec<Finding>,
+}
+
+fn content_digest(contents: &str) -> String {
+    format!("{:x}", Sha256::digest(contents.as_bytes()))
+}
+
+/// Modification time of the document, or `missing` if it cannot be read.
+fn document_state(statement: &str) -> String {
+    match std::fs::metadata(statement).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => {
+            let modified = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+            format!("{}.{}", modified.as_secs(), modified.subsec_nanos())
+        }
+        Err(_) => "missing".to_string(),
+    }
+}
+
+fn statements(directives: &[Sourced<Directive>]) -> Vec<String> {
+    directives
+        .iter()
+        .filter_map(|directive| match &directive.inner {
+            Directive::Transaction(txn) => match txn.meta.get("statement") {
+                Some(MetaValue::Text(statement)) => Some(statement.to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+impl LintCache {
+    /// Load the cache from `directory`, discarding it if it was written by
+    /// another version of autobean, or using other lint options.
+    pub fn load(directory: &Path, options: &LintOptions) -> Self {
+        let fresh = LintCache {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            options: format!(
+                "{:?}",
+                LintOptions {
+                    today: NaiveDate::MIN,
+                    ..options.clone()
+                }
+            ),
+            ..Default::default()
+        };
+
+        let cached: Option<LintCache> = std::fs::read_to_string(directory.join(CACHE_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok());
+
+        match cached {
+            Some(cached) if cached.version == fresh.version && cached.options == fresh.options => {
+                cached
+            }
+            _ => {
+                debug!(
+                    "no usable lint cache found in {}",
+                    directory.to_string_lossy()
+                );
+                fresh
+            }
+        }
+    }
+
+    pub fn save(&self, directory: &Path) -> Result<(), Error> {
+        std::fs::create_dir_all(directory)?;
+        let contents = serde_json::to_string(self).map_err(std::io::Error::from)?;
+        std::fs::write(directory.join(CACHE_FILE), contents)?;
+
+        Ok(())
+    }
+
+    fn cross_file_key(&self, options: &LintOptions) -> String {
+        let mut hasher = Sha256::new();
+
+        // Only budgets are checked against the date, so it is left out of the
+        // options the whole cache is keyed on.
+        if options.over_budget {
+            hasher.update(options.today.to_string().as_bytes());
+            hasher.update(b"\0");
+        }
+
+        for (path, file) in &self.files {
+            hasher.update(path.to_string_lossy().as_bytes());
+            hasher.update(b"\0");
+            hasher.update(file.digest.as_bytes());
+            hasher.update(b"\0");
+
+            for statement in &file.statements {
+                hasher.update(statement.as_bytes());
+                hasher.update(b"\0");
+                hasher.update(document_state(statement).as_bytes());
+                hasher.update(b"\0");
+            }
+        }
+
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// Run the lints, re-using the findings of the previous run for files whose contents
+/// have not changed. The cross-file lints are re-run if any file, or any document
+/// referred to by the ledger, has changed. Findings are returned in the same order
+/// as those of [`lints::run`].
+///
+/// Panics if a changed file fails to parse, like [`Ledger::directives`].
+pub fn check(
+    ledger: &Ledger,
+    directory: &Path,
+    hashes: &mut DocumentHashes,
+    options: &LintOptions,
+) -> Vec<Finding> {
+    let mut cache = LintCache::load(directory, options);
+
+    // Entries of files which no longer exist are dropped along with the old map.
+    let mut previous = std::mem::take(&mut cache.files);
+    let mut rechecked = 0;
+
+    for file in ledger.files() {
+        let digest = content_digest(&file.text_contents);
+
+        let cached = match previous.remove(&file.filename()) {
+            Some(cached) if cached.digest == digest => cached,
+            _ => {
+                rechecked += 1;
+                let directives = match ledger.parsed(&file.filename()) {
+                    Some(Ok(directives)) => directives,
+                    Some(Err(err)) => panic!("{}: {}", file.filename().to_string_lossy(), err),
+                    None => unreachable!("the file is part of the ledger"),
+                };
+
+                CachedFile {
+                    digest,
+                    statements: statements(directives),
+                    findings: lints::run_file_local(&[file], directives, options)
+                        .iter()
+                        .map(Finding::from)
+                        .collect(),
+                }
+            }
+        };
+
+        cache.files.insert(file.filename(), cached);
+    }
+
+    info!(
+        "checked {} changed files out of {}",
+        rechecked,
+        ledger.files().len()
+    );
+
+    let cross_file_digest = cache.cross_file_key(options);
+    if cache.cross_file_digest != cross_file_digest {
+        // Re-uses the directives of the files parsed above.
+        cache.cross_file_findings = lints::run_cross_file(ledger.directives(), hashes, options)
+            .iter()
+            .map(Finding::from)
+            .collect();
+        cache.cross_file_digest = cross_file_digest;
+    } else {
+        info!("re-using cached findings of cross-file lints");
+    }
+
+    if let Err(err) = cache.save(directory) {
+        warn!("failed to save lint cache: {}", err);
+    }
+
+    // Each file-local lint runs across every file in turn.
+    let mut file_local: Vec<_> = ledger
+        .files()
+        .filter_map(|file| cache.files.get(&file.filename()))
+        .flat_map(|file| &file.findings)
+        .collect();
+    file_local.sort_by_key(|finding| {
+        FILE_LOCAL_KINDS
+            .iter()
+            .position(|kind| *kind == finding.kind)
+    });
+
+    cache
+        .cross_file_findings
+        .iter()
+        .chain(file_local)
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::check;
+    use crate::{
+        appendix::hashes::DocumentHashes,
+        ledger::Ledger,
+        lints::{self, LintOptions},
+    };
+
+    #[test]
+    fn test_lint_cache() {
+        let root = std::path::PathBuf::from("target/test-cache");
+        std::fs::remove_dir_all(&root).ok();
+        std::fs::create_dir_all(&root).unwrap();
+
+        let transaction =
+            "2000-01-01 * \"Example Payee\" \"\"\n  Assets:Bank:Account  -1500 DKK\n  Assets:Bank:Savings\n";
+        std::fs::write(root.join("a.beancount"), transaction).unwrap();
+        std::fs::write(root.join("b.beancount"), "").unwrap();
+
+        let options = LintOptions {
+            double_entry_window: 0,
+            near_duplicates: false,
+            near_duplicate_window: 1,
+            near_duplicate_similarity: 80,
+            verify_amounts: false,
+            chronological: None,
+            over_budget: false,
+            today: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+        };
+        let cache = root.join(".autobean-cache");
+        let mut hashes = DocumentHashes::default();
+
+        let ledger = Ledger::from_path(&root).unwrap();
+        let first = check(&ledger, &cache, &mut hashes, &options);
+        let second = check(&ledger, &cache, &mut hashes, &options);
+        assert_eq!(first, second);
+        assert!(!first
+            .iter()
+            .any(|finding| finding.summary.contains("identical transaction")));
+
+        // Copying the transaction into another file only changes that file,
+        // but has to be picked up by the cross-file lints.
+        std::fs::write(root.join("b.beancount"), format!("{}  \n", transaction)).unwrap();
+        let ledger = Ledger::from_path(&root).unwrap();
+        let third = check(&ledger, &cache, &mut hashes, &options);
+        assert!(third
+            .iter()
+            .any(|finding| finding.summary.contains("identical transaction")));
+
+        // Cached findings are reported in the same order as those of a full run.
+        let files: Vec<_> = ledger.files().collect();
+        let uncached: Vec<_> = lints::run(&files, ledger.directives(), &mut hashes, &options)
+            .iter()
+            .map(|lint| lint.summary())
+            .collect();
+        let cached: Vec<_> = check(&ledger, &cache, &mut hashes, &options)
+            .into_iter()
+            .map(|finding| finding.summary)
+            .collect();
+        assert_eq!(cached, uncached);
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use beancount_core::{Account, Directive};
+use log::debug;
+
+use crate::{
+    edit::FileEdit,
+    import::classifier::{description, Classifier},
+    ledger::{Ledger, Sourced},
+    readable::account_name,
+};
+
+/// Re-book the postings to the `uncategorized` account of each transaction to
+/// the account suggested by the classifier, if it is at least `confidence` sure.
+pub fn categorize_ledger<'a>(
+    ledger: &'a Ledger,
+    directives: &[Sourced<'a, Directive<'a>>],
+    classifier: &Classifier,
+    uncategorized: &Account,
+    confidence: f64,
+) -> Vec<FileEdit<'a>> {
+    let uncategorized = account_name(uncategorized);
+
+    ledger
+        .files()
+        .map(|file| {
+            let mut suggestions = HashMap::new();
+
+            for directive in directives
+                .iter()
+                .filter(|directive| directive.location.ledger() == file)
+            {
+                let Directive::Transaction(transaction) = &directive.inner else {
+                    continue;
+                };
+
+                if !transaction
+                    .postings
+                    .iter()
+                    .any(|posting| account_name(&posting.account) == uncategorized)
+                {
+                    continue;
+                }
+
+                let text = description(transaction);
+                match classifier.classify(&text) {
+                    Some((account, probability)) if probability >= confidence => {
+                        debug!(
+                            "booking {} to {} with {:.0}% confidence",
+                            text.trim(),
+                            account_name(account),
+                            probability * 100.0
+                        );
+
+                        let location = &directive.location;
+                        for line in location.start()..location.end() {
+                            suggestions.insert(line as usize, account_name(account));
+                        }
+                    }
+                    _ => debug!("no confident suggestion for {}", text.trim()),
+                }
+            }
+
+            FileEdit::from_lines(file, |line_number, line| {
+                let account = suggestions.get(&line_number)?;
+                let posting = line.trim_start();
+                let indentation = &line[..line.len() - posting.len()];
+
+                posting
+                    .strip_prefix(uncategorized.as_str())
+                    .filter(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+                    .map(|rest| format!("{}{}{}", indentation, account, rest))
+            })
+        })
+        .filter(|edit| !edit.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::categorize_ledger;
+    use crate::{
+        import::{classifier::Classifier, parse_account},
+        inline_ledger,
+    };
+
+    #[test]
+    fn test_categorize() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 * "Netto" "Groceries"
+            Assets:Bank:Account  -150 DKK
+            Expenses:Food
+
+        2000-01-02 * "Netto" ""
+            Assets:Bank:Account  -200 DKK
+            Expenses:Uncategorized
+        "#
+        );
+
+        let directives = ledger.directives();
+        let uncategorized = parse_account("Expenses:Uncategorized").unwrap();
+        let classifier = Classifier::learn(&directives, &uncategorized);
+
+        let edits = categorize_ledger(&ledger, &directives, &classifier, &uncategorized, 0.8);
+        assert_eq!(edits.len(), 1);
+        assert!(!edits[0].contents.contains("Uncategorized"));
+        assert_eq!(edits[0].contents.matches("    Expenses:Food\n").count(), 2);
+    }
+}
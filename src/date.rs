@@ -0,0 +1,35 @@
+use beancount_core::{Date, Directive};
+use chrono::{Datelike, NaiveDate};
+
+/// Parse a beancount date, for when we need to do arithmetic on it.
+pub fn naive_date(date: &Date) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&date.to_string(), "%Y-%m-%d").ok()
+}
+
+/// Number of days since the beginning of the common era, which makes it
+/// cheap to compute the distance between two dates.
+pub fn day_number(date: &Date) -> Option<i64> {
+    naive_date(date).map(|date| date.num_days_from_ce() as i64)
+}
+
+/// Date of the directive, for those directives which have one.
+pub fn directive_date<'a>(directive: &'a Directive<'a>) -> Option<&'a Date<'a>> {
+    match directive {
+        Directive::Open(inner) => Some(&inner.date),
+        Directive::Close(inner) => Some(&inner.date),
+        Directive::Balance(inner) => Some(&inner.date),
+        Directive::Commodity(inner) => Some(&inner.date),
+        Directive::Custom(inner) => Some(&inner.date),
+        Directive::Document(inner) => Some(&inner.date),
+        Directive::Event(inner) => Some(&inner.date),
+        Directive::Note(inner) => Some(&inner.date),
+        Directive::Pad(inner) => Some(&inner.date),
+        Directive::Price(inner) => Some(&inner.date),
+        Directive::Query(inner) => Some(&inner.date),
+        Directive::Transaction(inner) => Some(&inner.date),
+        Directive::Option(_)
+        | Directive::Include(_)
+        | Directive::Plugin(_)
+        | Directive::Unsupported => None,
+    }
+}
@@ -0,0 +1,151 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use similar::TextDiff;
+
+use crate::{
+    error::Error,
+    ledger::{LedgerFile, LedgerSource},
+    location::Location,
+};
+
+/// Replacement of the lines covered by `location` with `replacement`, which must
+/// include its own trailing newline. An empty replacement removes the lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit<'a> {
+    pub location: Location<'a>,
+    pub replacement: String,
+}
+
+impl<'a> TextEdit<'a> {
+    pub fn delete(location: Location<'a>) -> Self {
+        TextEdit {
+            location,
+            replacement: String::new(),
+        }
+    }
+}
+
+/// A complete rewrite of the contents of a single ledger file.
+#[derive(Debug)]
+pub struct FileEdit<'a> {
+    pub ledger: &'a LedgerFile,
+    pub contents: String,
+}
+
+impl<'a> FileEdit<'a> {
+    /// Rewrite individual lines of `ledger`, leaving the rest of the file untouched.
+    /// `rewrite` is called with the zero-indexed line number and the line itself,
+    /// including its line ending, and returns the replacement if any.
+    pub fn from_lines(
+        ledger: &'a LedgerFile,
+        mut rewrite: impl FnMut(usize, &str) -> Option<String>,
+    ) -> Self {
+        let contents = ledger
+            .original_contents()
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(line_number, line)| {
+                rewrite(line_number, line).unwrap_or_else(|| line.to_string())
+            })
+            .collect();
+
+        FileEdit { ledger, contents }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ledger.original_contents() == self.contents
+    }
+
+    /// Render the edit as a unified diff against the original file.
+    pub fn diff(&self) -> String {
+        let filename = self.ledger.filename().to_string_lossy().to_string();
+
+        TextDiff::from_lines(self.ledger.original_contents(), &self.contents)
+            .unified_diff()
+            .context_radius(3)
+            .header(&filename, &filename)
+            .to_string()
+    }
+
+    /// Write the edited contents back to the file the ledger was read from.
+    pub fn apply(&self) -> Result<(), Error> {
+        self.write(&self.contents)
+    }
+
+    /// Undo an applied edit, writing the original contents back.
+    pub fn revert(&self) -> Result<(), Error> {
+        self.write(self.ledger.original_contents())
+    }
+
+    fn write(&self, contents: &str) -> Result<(), Error> {
+        match &self.ledger.source {
+            LedgerSource::File(path) => std::fs::write(path, contents)?,
+            #[cfg(test)]
+            LedgerSource::Code { .. } => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// Combine the edits fixing each finding into a single edit per file. Findings
+/// whose edits overlap those of an earlier finding are skipped as a whole, since
+/// applying both would produce garbage, and returned so they can be reported.
+pub fn combine<'a, T>(
+    fixes: impl IntoIterator<Item = (T, Vec<TextEdit<'a>>)>,
+) -> (Vec<FileEdit<'a>>, Vec<T>) {
+    let mut by_file: BTreeMap<PathBuf, (&'a LedgerFile, Vec<TextEdit<'a>>)> = BTreeMap::new();
+    let mut skipped = Vec::new();
+
+    for (finding, edits) in fixes {
+        let overlaps = |a: &TextEdit, b: &TextEdit| {
+            a.location.ledger().filename() == b.location.ledger().filename()
+                && a.location.start() < b.location.end().max(b.location.start() + 1)
+                && b.location.start() < a.location.end().max(a.location.start() + 1)
+        };
+
+        let conflicting = edits.iter().enumerate().any(|(index, edit)| {
+            edits[..index].iter().any(|other| overlaps(edit, other))
+                || by_file
+                    .get(&edit.location.ledger().filename())
+                    .is_some_and(|(_, accepted)| accepted.iter().any(|other| overlaps(edit, other)))
+        });
+
+        if conflicting {
+            skipped.push(finding);
+            continue;
+        }
+
+        for edit in edits {
+            by_file
+                .entry(edit.location.ledger().filename())
+                .or_insert_with(|| (edit.location.ledger(), Vec::new()))
+                .1
+                .push(edit);
+        }
+    }
+
+    let edits = by_file
+        .into_values()
+        .map(|(ledger, mut edits)| {
+            edits.sort_by(|a, b| a.location.cmp(&b.location));
+
+            let lines: Vec<_> = ledger.original_contents().split_inclusive('\n').collect();
+            let mut contents = String::new();
+            let mut line = 0;
+            for edit in edits {
+                let (start, end) = (edit.location.start() as usize, edit.location.end() as usize);
+
+                contents.extend(lines[line..start.min(lines.len())].iter().copied());
+                contents.push_str(&edit.replacement);
+                line = end.max(start);
+            }
+            contents.extend(lines[line.min(lines.len())..].iter().copied());
+
+            FileEdit { ledger, contents }
+        })
+        .filter(|edit| !edit.is_empty())
+        .collect();
+
+    (edits, skipped)
+}
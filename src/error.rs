@@ -9,4 +9,22 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error("loading ledger {0}, {1}")]
     Ledger(PathBuf, ParseError),
+    #[error("invalid date {0}, expected YYYY-MM-DD")]
+    InvalidDate(String),
+    #[error("document {0} already exists")]
+    DocumentExists(PathBuf),
+    #[error("watching ledger: {0}")]
+    Watch(#[from] notify_debouncer_mini::notify::Error),
+    #[error("language server: {0}")]
+    Lsp(String),
+    #[error("invalid account {0}, expected e.g. Assets:Bank")]
+    InvalidAccount(String),
+    #[error("importing {0}, {1}")]
+    Import(PathBuf, String),
+    #[error("rendering directives: {0}")]
+    Render(String),
+    #[error("query: {0}")]
+    Query(String),
+    #[error("no price attached to {0}")]
+    MissingPrice(String),
 }
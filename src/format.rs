@@ -0,0 +1,352 @@
+use crate::{edit::FileEdit, ledger::Ledger};
+
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Column at which posting amounts end.
+    pub column: usize,
+    /// Number of spaces postings and metadata are indented by.
+    pub indent: usize,
+}
+
+enum Item<'s> {
+    Blank,
+    Comment(&'s str),
+    Directive { header: &'s str, body: Vec<&'s str> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Previous {
+    Comment,
+    SingleLine,
+    MultiLine,
+}
+
+/// Split a line into its code and trailing comment, if any, ignoring semicolons
+/// within quoted strings.
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => return (&line[..i], Some(&line[i..])),
+            _ => {}
+        }
+    }
+
+    (line, None)
+}
+
+/// Whether the line opens or closes a string spanning multiple lines.
+fn has_unbalanced_quotes(line: &str) -> bool {
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for c in line.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ => {}
+        }
+    }
+
+    quoted
+}
+
+/// Collapse runs of whitespace outside of quoted strings into single spaces.
+fn collapse_whitespace(code: &str) -> String {
+    let mut collapsed = String::with_capacity(code.len());
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut space = false;
+
+    for c in code.trim().chars() {
+        if !quoted && c.is_whitespace() {
+            space = true;
+            continue;
+        }
+
+        if space {
+            collapsed.push(' ');
+            space = false;
+        }
+
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ => {}
+        }
+
+        collapsed.push(c);
+    }
+
+    collapsed
+}
+
+fn with_comment(code: String, comment: Option<&str>) -> String {
+    match comment {
+        Some(comment) if code.is_empty() => comment.trim_end().to_string(),
+        Some(comment) => format!("{} {}", code, comment.trim_end()),
+        None => code,
+    }
+}
+
+/// Key of a `key: value` metadata line, if the line is one.
+fn metadata_key(line: &str) -> Option<&str> {
+    let (key, _) = line.split_once(':')?;
+
+    (key.starts_with(|c: char| c.is_ascii_lowercase())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+    .then_some(key)
+}
+
+fn format_posting(code: &str, indent: &str, column: usize) -> String {
+    let code = collapse_whitespace(code);
+    let mut prefix = String::from(indent);
+    let mut rest = code.as_str();
+
+    // Postings may be flagged, e.g. `! Assets:Bank`
+    if let Some((flag, remainder)) = rest.split_once(' ') {
+        if flag.chars().count() == 1 && !flag.starts_with(char::is_alphanumeric) {
+            prefix.push_str(flag);
+            prefix.push(' ');
+            rest = remainder;
+        }
+    }
+
+    let (account, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    prefix.push_str(account);
+
+    if rest.is_empty() {
+        return prefix;
+    }
+
+    let number = rest.split(' ').next().unwrap_or_default();
+    let padding = if number.starts_with(|c: char| c.is_ascii_digit() || "-+.(".contains(c)) {
+        column
+            .saturating_sub(prefix.chars().count() + number.chars().count())
+            .max(2)
+    } else {
+        2
+    };
+
+    format!("{}{}{}", prefix, " ".repeat(padding), rest)
+}
+
+fn format_directive(header: &str, body: &[&str], options: &FormatOptions) -> Vec<String> {
+    // Multi-line strings are rare enough that it is not worth the trouble of
+    // formatting around them, so such directives are left as they are.
+    if has_unbalanced_quotes(header) || body.iter().any(|line| has_unbalanced_quotes(line)) {
+        return [header]
+            .iter()
+            .chain(body)
+            .map(|line| line.trim_end().to_string())
+            .collect();
+    }
+
+    let (code, comment) = split_comment(header);
+    let mut lines = vec![with_comment(collapse_whitespace(code), comment)];
+
+    let indentation = |line: &str| line.len() - line.trim_start().len();
+    let base = body
+        .iter()
+        .map(|&line| indentation(line))
+        .min()
+        .unwrap_or(0);
+
+    // (level, metadata key, formatted line)
+    let mut formatted: Vec<(usize, Option<&str>, String)> = body
+        .iter()
+        .map(|&line| {
+            let level = if indentation(line) > base { 2 } else { 1 };
+            let indent = " ".repeat(options.indent * level);
+            let line = line.trim();
+
+            if line.starts_with(';') {
+                (level, None, format!("{}{}", indent, line))
+            } else if let Some(key) = metadata_key(line) {
+                let (code, comment) = split_comment(&line[key.len() + 1..]);
+                let value = collapse_whitespace(code);
+                let metadata = if value.is_empty() {
+                    format!("{}{}:", indent, key)
+                } else {
+                    format!("{}{}: {}", indent, key, value)
+                };
+
+                (level, Some(key), with_comment(metadata, comment))
+            } else {
+                let (code, comment) = split_comment(line);
+                let posting = format_posting(code, &indent, options.column);
+
+                (level, None, with_comment(posting, comment))
+            }
+        })
+        .collect();
+
+    // Sort each consecutive run of metadata at the same level by key.
+    let mut start = 0;
+    while start < formatted.len() {
+        let mut end = start + 1;
+        if formatted[start].1.is_some() {
+            while end < formatted.len()
+                && formatted[end].1.is_some()
+                && formatted[end].0 == formatted[start].0
+            {
+                end += 1;
+            }
+            formatted[start..end].sort_by_key(|(_, key, _)| *key);
+        }
+        start = end;
+    }
+
+    lines.extend(formatted.into_iter().map(|(_, _, line)| line));
+    lines
+}
+
+/// Format beancount source, normalizing whitespace within directives, aligning
+/// posting amounts, sorting metadata and separating directives by a single blank line.
+/// Comments are kept in place.
+pub fn format_source(source: &str, options: &FormatOptions) -> String {
+    let mut items = Vec::new();
+
+    for line in source.lines() {
+        if line.trim().is_empty() {
+            items.push(Item::Blank);
+        } else if line.starts_with(char::is_whitespace) {
+            match items.last_mut() {
+                Some(Item::Directive { body, .. }) => body.push(line),
+                _ => items.push(Item::Comment(line)),
+            }
+        } else if line.starts_with([';', '#', '*']) {
+            items.push(Item::Comment(line));
+        } else {
+            items.push(Item::Directive {
+                header: line,
+                body: Vec::new(),
+            });
+        }
+    }
+
+    let mut formatted = String::with_capacity(source.len());
+    let mut previous = None;
+    let mut blank = false;
+
+    for item in items {
+        let (current, lines) = match item {
+            Item::Blank => {
+                blank = true;
+                continue;
+            }
+            Item::Comment(line) => (Previous::Comment, vec![line.trim().to_string()]),
+            Item::Directive { header, body } => (
+                if body.is_empty() {
+                    Previous::SingleLine
+                } else {
+                    Previous::MultiLine
+                },
+                format_directive(header, &body, options),
+            ),
+        };
+
+        let separate = match previous {
+            None => false,
+            Some(Previous::MultiLine) => true,
+            Some(Previous::SingleLine) => blank || current == Previous::MultiLine,
+            Some(Previous::Comment) => blank,
+        };
+
+        if separate {
+            formatted.push('\n');
+        }
+
+        for line in lines {
+            formatted.push_str(&line);
+            formatted.push('\n');
+        }
+
+        previous = Some(current);
+        blank = false;
+    }
+
+    formatted
+}
+
+/// Format every file of the ledger, returning edits for the files which change.
+pub fn format_ledger<'a>(ledger: &'a Ledger, options: &FormatOptions) -> Vec<FileEdit<'a>> {
+    ledger
+        .files()
+        .map(|file| FileEdit {
+            ledger: file,
+            contents: format_source(file.original_contents(), options),
+        })
+        .filter(|edit| !edit.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_source, FormatOptions};
+
+    #[test]
+    fn test_format() {
+        let options = FormatOptions {
+            column: 40,
+            indent: 2,
+        };
+
+        let source = unindent::unindent(
+            r#"
+
+        option "title"   "Example"
+
+        ;; Opening accounts
+        2000-01-01 open   Assets:Bank:Account
+        2000-01-01 open Expenses:Office
+        2000-01-02 *   "ACME  Corp"  "Office chair"   ; reimbursed
+            statement: "documents/2000-01-02.1.chair.pdf"
+            approved:   TRUE
+            Expenses:Office   1500.00 DKK
+            Assets:Bank:Account  ; paid by card
+        2000-01-03 * "Grocery Store" ""
+              Assets:Bank:Account  -25 DKK
+              Expenses:Groceries
+
+
+
+        ; trailing comment
+        "#,
+        );
+
+        let formatted = format_source(&source, &options);
+        println!("{}", formatted);
+
+        let lines: Vec<_> = formatted.lines().collect();
+        assert_eq!(lines[0], r#"option "title" "Example""#);
+        assert_eq!(lines[1], "");
+        assert_eq!(lines[2], ";; Opening accounts");
+        assert_eq!(lines[4], "2000-01-01 open Expenses:Office");
+        assert_eq!(lines[5], "");
+        assert_eq!(
+            lines[6],
+            r#"2000-01-02 * "ACME  Corp" "Office chair" ; reimbursed"#
+        );
+        assert_eq!(lines[7], "  approved: TRUE");
+        assert!(lines[8].starts_with("  statement: "));
+        assert_eq!(lines[9].find("1500.00"), Some(40 - "1500.00".len()));
+        assert_eq!(lines[10], "  Assets:Bank:Account ; paid by card");
+        assert_eq!(lines[11], "");
+        assert_eq!(lines[13].find("-25"), Some(40 - "-25".len()));
+        assert_eq!(lines[15], "");
+        assert_eq!(lines[16], "; trailing comment");
+        assert_eq!(lines.len(), 17);
+
+        assert_eq!(format_source(&formatted, &options), formatted);
+    }
+}
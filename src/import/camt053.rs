@@ -0,0 +1,216 @@
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use roxmltree::{Document, Node};
+use rust_decimal::Decimal;
+
+use super::{ClosingBalance, Entry, Reference, Statement};
+
+/// Follow the path of element names down from `node`, ignoring namespaces.
+fn find<'a, 'input>(node: Node<'a, 'input>, path: &[&str]) -> Option<Node<'a, 'input>> {
+    path.iter().try_fold(node, |node, name| {
+        node.children().find(|child| child.has_tag_name(*name))
+    })
+}
+
+fn text<'a>(node: Node<'a, '_>, path: &[&str]) -> Option<&'a str> {
+    find(node, path)
+        .and_then(|node| node.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+fn date(node: Node, path: &[&str]) -> Option<NaiveDate> {
+    let node = find(node, path)?;
+
+    // Either a plain date, or a date and time.
+    let date = text(node, &["Dt"]).or_else(|| text(node, &["DtTm"]))?;
+    NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// Amount of the node, negative if debited.
+fn amount(node: Node) -> Option<Decimal> {
+    let amount = Decimal::from_str(text(node, &["Amt"])?).ok()?;
+
+    match text(node, &["CdtDbtInd"])? {
+        "DBIT" => Some(-amount),
+        _ => Some(amount),
+    }
+}
+
+fn entry(node: Node) -> Result<Entry, String> {
+    let position = node.document().text_pos_at(node.range().start);
+    let error = |field: &str| format!("line {}: missing or invalid {}", position.row, field);
+
+    let details = find(node, &["NtryDtls", "TxDtls"]);
+
+    // Prefer the text the bank shows in its own overview, then the remittance
+    // information, and finally the name of the other party.
+    let description = text(node, &["AddtlNtryInf"])
+        .or_else(|| details.and_then(|details| text(details, &["AddtlTxInf"])))
+        .or_else(|| details.and_then(|details| text(details, &["RmtInf", "Ustrd"])))
+        .or_else(|| {
+            details.and_then(|details| {
+                text(details, &["RltdPties", "Cdtr", "Nm"])
+                    .or_else(|| text(details, &["RltdPties", "Dbtr", "Nm"]))
+            })
+        })
+        .unwrap_or_default();
+
+    let reference = text(node, &["AcctSvcrRef"])
+        .or_else(|| text(node, &["NtryRef"]))
+        .or_else(|| details.and_then(|details| text(details, &["Refs", "AcctSvcrRef"])))
+        .map(Reference::bank);
+
+    Ok(Entry {
+        date: date(node, &["BookgDt"])
+            .or_else(|| date(node, &["ValDt"]))
+            .ok_or_else(|| error("booking date"))?,
+        text: description.to_string(),
+        amount: amount(node).ok_or_else(|| error("amount"))?,
+        reference,
+    })
+}
+
+/// Identifier of the account a statement belongs to.
+fn account<'a>(statement: Node<'a, '_>) -> Option<&'a str> {
+    text(statement, &["Acct", "Id", "IBAN"])
+        .or_else(|| text(statement, &["Acct", "Id", "Othr", "Id"]))
+}
+
+/// Parse every statement of the document, such as one per day, which must all
+/// belong to the same account.
+pub fn parse(contents: &str) -> Result<Statement, String> {
+    let document = Document::parse(contents).map_err(|err| err.to_string())?;
+
+    let statements: Vec<_> = document
+        .descendants()
+        .filter(|node| node.has_tag_name("Stmt"))
+        .collect();
+
+    let first = *statements.first().ok_or("no statement found in document")?;
+
+    if let Some(other) = statements
+        .iter()
+        .find(|statement| account(**statement) != account(first))
+    {
+        return Err(format!(
+            "statements of several accounts ({} and {}), please import them separately",
+            account(first).unwrap_or("unknown"),
+            account(*other).unwrap_or("unknown")
+        ));
+    }
+
+    let entries = statements
+        .iter()
+        .flat_map(|statement| statement.children())
+        .filter(|node| node.has_tag_name("Ntry"))
+        .map(entry)
+        .collect::<Result<_, _>>()?;
+
+    // The closing booked balance, as opposed to the opening or available balances,
+    // of the last statement.
+    let closing_balance = statements
+        .iter()
+        .flat_map(|statement| statement.children())
+        .filter(|node| node.has_tag_name("Bal"))
+        .filter(|node| text(*node, &["Tp", "CdOrPrtry", "Cd"]) == Some("CLBD"))
+        .filter_map(|node| {
+            Some(ClosingBalance {
+                date: date(node, &["Dt"])?,
+                amount: amount(node)?,
+            })
+        })
+        .max_by_key(|balance| balance.date);
+
+    let currency = text(first, &["Acct", "Ccy"]).or_else(|| {
+        first
+            .descendants()
+            .find(|node| node.has_tag_name("Amt"))
+            .and_then(|node| node.attribute("Ccy"))
+    });
+
+    Ok(Statement {
+        currency: currency.map(str::to_string),
+        entries,
+        closing_balance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::parse;
+
+    #[test]
+    fn test_parse_camt053() {
+        let contents = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+              <BkToCstmrStmt>
+                <Stmt>
+                  <Acct><Ccy>DKK</Ccy></Acct>
+                  <Bal>
+                    <Tp><CdOrPrtry><Cd>OPBD</Cd></CdOrPrtry></Tp>
+                    <Amt Ccy="DKK">10000.00</Amt>
+                    <CdtDbtInd>CRDT</CdtDbtInd>
+                    <Dt><Dt>2000-01-01</Dt></Dt>
+                  </Bal>
+                  <Bal>
+                    <Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp>
+                    <Amt Ccy="DKK">8500.00</Amt>
+                    <CdtDbtInd>CRDT</CdtDbtInd>
+                    <Dt><Dt>2000-01-31</Dt></Dt>
+                  </Bal>
+                  <Ntry>
+                    <Amt Ccy="DKK">1500.00</Amt>
+                    <CdtDbtInd>DBIT</CdtDbtInd>
+                    <BookgDt><Dt>2000-01-03</Dt></BookgDt>
+                    <AcctSvcrRef>2000010312345</AcctSvcrRef>
+                    <NtryDtls><TxDtls><RmtInf><Ustrd>Example Payee</Ustrd></RmtInf></TxDtls></NtryDtls>
+                  </Ntry>
+                </Stmt>
+                <Stmt>
+                  <Acct><Ccy>DKK</Ccy></Acct>
+                  <Bal>
+                    <Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp>
+                    <Amt Ccy="DKK">8250.00</Amt>
+                    <CdtDbtInd>CRDT</CdtDbtInd>
+                    <Dt><Dt>2000-02-29</Dt></Dt>
+                  </Bal>
+                  <Ntry>
+                    <Amt Ccy="DKK">250.00</Amt>
+                    <CdtDbtInd>DBIT</CdtDbtInd>
+                    <BookgDt><Dt>2000-02-03</Dt></BookgDt>
+                    <AddtlNtryInf>Other Payee</AddtlNtryInf>
+                  </Ntry>
+                </Stmt>
+              </BkToCstmrStmt>
+            </Document>"#;
+
+        let statement = parse(contents).unwrap();
+        assert_eq!(statement.currency.as_deref(), Some("DKK"));
+        assert_eq!(statement.entries.len(), 2);
+
+        let entry = &statement.entries[0];
+        assert_eq!(entry.text, "Example Payee");
+        assert_eq!(entry.amount, Decimal::new(-1500, 0));
+        assert_eq!(entry.reference.as_ref().unwrap().value, "2000010312345");
+
+        assert_eq!(statement.entries[1].text, "Other Payee");
+
+        // The closing balance is that of the last statement.
+        let closing = statement.closing_balance.unwrap();
+        assert_eq!(closing.date, NaiveDate::from_ymd_opt(2000, 2, 29).unwrap());
+        assert_eq!(closing.amount, Decimal::new(8250, 0));
+
+        // Statements of different accounts are not mixed.
+        let mixed = contents.replacen(
+            "<Acct><Ccy>DKK</Ccy></Acct>",
+            "<Acct><Id><IBAN>DK5000400440116243</IBAN></Id><Ccy>DKK</Ccy></Acct>",
+            1,
+        );
+        assert!(parse(&mixed).is_err());
+    }
+}
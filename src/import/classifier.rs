@@ -0,0 +1,194 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use beancount_core::{Account, AccountType, Directive, Transaction};
+use log::debug;
+
+use crate::{ledger::Sourced, readable::account_name};
+
+use super::parse_account;
+
+/// Lowercase words of the text, leaving out numbers and single characters,
+/// which tend to be dates and reference numbers rather than telling anything
+/// about the purchase.
+fn tokens(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| token.chars().count() > 1)
+        .filter(|token| !token.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_lowercase)
+}
+
+/// Text a transaction is classified by.
+pub fn description(transaction: &Transaction) -> String {
+    format!(
+        "{} {}",
+        transaction.payee.as_deref().unwrap_or_default(),
+        transaction.narration
+    )
+}
+
+#[derive(Debug, Default)]
+struct Class {
+    account: Option<Account<'static>>,
+    /// Number of transactions booked to the account.
+    transactions: usize,
+    /// Occurrences of each word in those transactions.
+    words: HashMap<String, usize>,
+    total_words: usize,
+}
+
+/// Naive Bayes classifier over the words of payees and narrations, learning
+/// which expense or income account transactions are booked to from the
+/// history of the ledger.
+#[derive(Debug, Default)]
+pub struct Classifier {
+    classes: HashMap<String, Class>,
+    vocabulary: HashSet<String>,
+    transactions: usize,
+}
+
+impl Classifier {
+    pub fn train(&mut self, text: &str, account: &Account) {
+        let name = account_name(account);
+        let class = self.classes.entry(name.clone()).or_default();
+
+        if class.account.is_none() {
+            class.account = parse_account(&name).ok();
+        }
+
+        class.transactions += 1;
+        for token in tokens(text) {
+            *class.words.entry(token.clone()).or_default() += 1;
+            class.total_words += 1;
+            self.vocabulary.insert(token);
+        }
+
+        self.transactions += 1;
+    }
+
+    /// Learn from every transaction booked to exactly one expense or income
+    /// account, other than `fallback`, where uncategorized postings end up.
+    pub fn learn(directives: &[Sourced<Directive>], fallback: &Account) -> Self {
+        let mut classifier = Classifier::default();
+
+        for directive in directives {
+            let Directive::Transaction(transaction) = &directive.inner else {
+                continue;
+            };
+
+            let categories: BTreeSet<_> = transaction
+                .postings
+                .iter()
+                .map(|posting| &posting.account)
+                .filter(|account| matches!(account.ty, AccountType::Expenses | AccountType::Income))
+                .map(account_name)
+                .collect();
+
+            if categories.len() == 1 && !categories.contains(&account_name(fallback)) {
+                let account = transaction
+                    .postings
+                    .iter()
+                    .map(|posting| &posting.account)
+                    .find(|account| categories.contains(&account_name(account)));
+
+                if let Some(account) = account {
+                    classifier.train(&description(transaction), account);
+                }
+            }
+        }
+
+        debug!(
+            "learned {} accounts from {} transactions",
+            classifier.classes.len(),
+            classifier.transactions
+        );
+
+        classifier
+    }
+
+    /// Most likely account for a transaction with this text, along with the
+    /// probability of it being the right one. Text without any known words
+    /// has no suggestion, rather than whichever account is most common.
+    pub fn classify(&self, text: &str) -> Option<(&Account<'static>, f64)> {
+        let tokens: Vec<_> = tokens(text)
+            .filter(|token| self.vocabulary.contains(token))
+            .collect();
+
+        if tokens.is_empty() {
+            return None;
+        }
+
+        let vocabulary = self.vocabulary.len() as f64;
+        let scores: Vec<_> = self
+            .classes
+            .values()
+            .filter_map(|class| {
+                let prior = (class.transactions as f64 / self.transactions as f64).ln();
+                let likelihood: f64 = tokens
+                    .iter()
+                    .map(|token| {
+                        let count = class.words.get(token).copied().unwrap_or_default();
+                        ((count + 1) as f64 / (class.total_words as f64 + vocabulary)).ln()
+                    })
+                    .sum();
+
+                Some((class.account.as_ref()?, prior + likelihood))
+            })
+            .collect();
+
+        // Normalize the log probabilities, shifting them to avoid underflow.
+        let max = scores
+            .iter()
+            .map(|(_, score)| *score)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let total: f64 = scores.iter().map(|(_, score)| (score - max).exp()).sum();
+
+        scores
+            .into_iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(account, score)| (account, (score - max).exp() / total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Classifier;
+    use crate::{import::parse_account, inline_ledger, readable::account_name};
+
+    #[test]
+    fn test_classify() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 * "Netto" "Groceries"
+            Assets:Bank:Account  -150 DKK
+            Expenses:Food
+
+        2000-01-02 * "Netto" ""
+            Assets:Bank:Account  -200 DKK
+            Expenses:Food
+
+        2000-01-03 * "DSB" "Train to Aarhus"
+            Assets:Bank:Account  -400 DKK
+            Expenses:Travel
+
+        2000-01-04 * "Staples" ""
+            Assets:Bank:Account  -100 DKK
+            Expenses:Uncategorized
+        "#
+        );
+
+        let fallback = parse_account("Expenses:Uncategorized").unwrap();
+        let classifier = Classifier::learn(&ledger.directives(), &fallback);
+
+        let (account, confidence) = classifier.classify("NETTO 1234").unwrap();
+        assert_eq!(account_name(account), "Expenses:Food");
+        assert!(confidence > 0.8);
+
+        let (account, _) = classifier.classify("DSB Aarhus").unwrap();
+        assert_eq!(account_name(account), "Expenses:Travel");
+
+        // Uncategorized transactions are not learned from, so their payees are
+        // as unknown as any other, and get no suggestion.
+        assert!(classifier.classify("Staples").is_none());
+        assert!(classifier.classify("Unknown Kiosk 0042").is_none());
+    }
+}
@@ -0,0 +1,166 @@
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use clap::Args;
+use rust_decimal::Decimal;
+
+use super::{ClosingBalance, Entry, Statement};
+
+/// Layout of a CSV bank export. Columns are numbered from zero.
+#[derive(Debug, Clone, Args)]
+pub struct CsvOptions {
+    /// Column holding the booking date.
+    #[arg(long, default_value_t = 0)]
+    pub date_column: usize,
+    /// Format of the booking date, e.g. %d-%m-%Y.
+    #[arg(long, default_value = "%Y-%m-%d")]
+    pub date_format: String,
+    /// Column holding the text of the line.
+    #[arg(long, default_value_t = 1)]
+    pub text_column: usize,
+    /// Column holding the amount, negative for withdrawals.
+    #[arg(long, default_value_t = 2)]
+    pub amount_column: usize,
+    /// Column holding the balance after each line, used to assert the closing balance.
+    #[arg(long)]
+    pub balance_column: Option<usize>,
+    /// Amounts are written with a decimal comma, as in 1.234,56.
+    #[arg(long)]
+    pub decimal_comma: bool,
+    /// Character separating the fields of each line.
+    #[arg(long, default_value_t = ',')]
+    pub delimiter: char,
+    /// The first line holds column names rather than a statement line.
+    #[arg(long)]
+    pub header: bool,
+}
+
+/// Parse an amount, ignoring thousands separators. A trailing decimal
+/// separator, as in `250,`, is allowed.
+pub fn parse_amount(amount: &str, decimal_comma: bool) -> Option<Decimal> {
+    let amount: String = amount
+        .trim_end_matches(if decimal_comma { ',' } else { '.' })
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .filter(|c| *c != if decimal_comma { '.' } else { ',' })
+        .map(|c| if c == ',' { '.' } else { c })
+        .collect();
+
+    Decimal::from_str(&amount).ok()
+}
+
+pub fn parse(contents: &str, options: &CsvOptions) -> Result<Statement, String> {
+    if !options.delimiter.is_ascii() {
+        return Err(format!(
+            "delimiter {} is not an ascii character",
+            options.delimiter
+        ));
+    }
+
+    let mut reader = ::csv::ReaderBuilder::new()
+        .delimiter(options.delimiter as u8)
+        .has_headers(options.header)
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    let mut entries = Vec::new();
+    let mut balances = Vec::new();
+
+    for record in reader.records() {
+        let record = record.map_err(|err| err.to_string())?;
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+
+        // Skip blank lines and the like, which some banks append to their exports.
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+
+        let field = |column: usize| {
+            record
+                .get(column)
+                .map(str::trim)
+                .ok_or_else(|| format!("line {}: missing column {}", line, column))
+        };
+
+        let date = field(options.date_column)?;
+        let date = NaiveDate::parse_from_str(date, &options.date_format)
+            .map_err(|_| format!("line {}: invalid date {}", line, date))?;
+
+        let amount = field(options.amount_column)?;
+        let amount = parse_amount(amount, options.decimal_comma)
+            .ok_or_else(|| format!("line {}: invalid amount {}", line, amount))?;
+
+        if let Some(column) = options.balance_column {
+            let balance = field(column)?;
+            balances.push(
+                parse_amount(balance, options.decimal_comma)
+                    .ok_or_else(|| format!("line {}: invalid balance {}", line, balance))?,
+            );
+        }
+
+        entries.push(Entry {
+            date,
+            text: field(options.text_column)?.to_string(),
+            amount,
+            reference: None,
+        });
+    }
+
+    // Exports list either the newest or the oldest line first.
+    let newest_first = entries
+        .first()
+        .zip(entries.last())
+        .is_some_and(|(first, last)| first.date > last.date);
+
+    let closing = if newest_first {
+        entries.first().zip(balances.first())
+    } else {
+        entries.last().zip(balances.last())
+    };
+
+    let closing_balance = closing.map(|(entry, balance)| ClosingBalance {
+        date: entry.date,
+        amount: *balance,
+    });
+
+    Ok(Statement {
+        currency: None,
+        entries,
+        closing_balance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::{parse, CsvOptions};
+
+    #[test]
+    fn test_parse_csv() {
+        let options = CsvOptions {
+            date_column: 0,
+            date_format: "%d-%m-%Y".to_string(),
+            text_column: 1,
+            amount_column: 2,
+            balance_column: Some(3),
+            decimal_comma: true,
+            delimiter: ';',
+            header: true,
+        };
+
+        let contents = "Dato;Tekst;Beløb;Saldo\n\
+            03-01-2000;Netto;-1.234,50;8.765,50\n\
+            02-01-2000;Løn;10.000,00;10.000,00\n";
+
+        let statement = parse(contents, &options).unwrap();
+        assert_eq!(statement.entries.len(), 2);
+        assert_eq!(statement.entries[0].text, "Netto");
+        assert_eq!(statement.entries[0].amount, Decimal::new(-123450, 2));
+
+        let closing = statement.closing_balance.unwrap();
+        assert_eq!(closing.date, NaiveDate::from_ymd_opt(2000, 1, 3).unwrap());
+        assert_eq!(closing.amount, Decimal::new(876550, 2));
+    }
+}
@@ -0,0 +1,454 @@
+pub mod camt053;
+pub mod classifier;
+pub mod csv;
+pub mod mt940;
+pub mod ofx;
+pub mod rules;
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use beancount_core::{
+    metadata::MetaValue, Account, AccountType, Amount, Balance, Date, Directive, IncompleteAmount,
+    Posting, Transaction,
+};
+use chrono::{Days, NaiveDate};
+use clap::ValueEnum;
+use log::debug;
+use rust_decimal::Decimal;
+
+use crate::{error::Error, ledger::Sourced, lints::Fingerprint, readable::account_name};
+
+use rules::Rules;
+
+/// Identifier assigned to a statement line by the bank, stored in the metadata
+/// of the imported transaction under `key`, such as `reference` or `fitid`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub key: &'static str,
+    pub value: String,
+}
+
+impl Reference {
+    pub fn bank(value: &str) -> Self {
+        Reference {
+            key: "reference",
+            value: value.to_string(),
+        }
+    }
+}
+
+/// A single line of a bank statement, independent of the format it was exported in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub date: NaiveDate,
+    pub text: String,
+    pub amount: Decimal,
+    pub reference: Option<Reference>,
+}
+
+/// Balance of the account at the end of the given day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClosingBalance {
+    pub date: NaiveDate,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Statement {
+    /// Currency of the account, for formats which state it.
+    pub currency: Option<String>,
+    pub entries: Vec<Entry>,
+    pub closing_balance: Option<ClosingBalance>,
+}
+
+/// Format of a bank export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    Csv,
+    /// ISO 20022 camt.053 XML.
+    Camt053,
+    /// SWIFT MT940.
+    Mt940,
+    /// OFX or QFX, in either the SGML based version 1 or the XML based version 2.
+    Ofx,
+}
+
+impl ImportFormat {
+    /// Guess the format from the extension of the export.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "csv" => Some(ImportFormat::Csv),
+            "xml" | "camt" | "053" => Some(ImportFormat::Camt053),
+            "sta" | "mt940" | "940" => Some(ImportFormat::Mt940),
+            "ofx" | "qfx" => Some(ImportFormat::Ofx),
+            _ => None,
+        }
+    }
+}
+
+/// Character encoding of a bank export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    Utf8,
+    /// ISO-8859-1.
+    Latin1,
+    /// Windows-1252, which differs from ISO-8859-1 by the printable characters
+    /// it has in place of some control characters.
+    Cp1252,
+}
+
+/// Characters of Windows-1252 from 0x80 to 0x9f. Unassigned bytes are kept
+/// as the control characters they are in ISO-8859-1.
+const CP1252: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+impl Encoding {
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, String> {
+        match self {
+            Encoding::Utf8 => String::from_utf8(bytes.to_vec())
+                .map_err(|err| format!("{}, please specify its --encoding", err)),
+            Encoding::Latin1 => Ok(bytes.iter().copied().map(char::from).collect()),
+            Encoding::Cp1252 => Ok(bytes
+                .iter()
+                .map(|&byte| match byte {
+                    0x80..=0x9f => CP1252[usize::from(byte - 0x80)],
+                    _ => char::from(byte),
+                })
+                .collect()),
+        }
+    }
+}
+
+/// Read the bank export at `path`, detecting its format unless given. The
+/// export must be valid UTF-8, unless another encoding is given, or declared
+/// by the header of an OFX export.
+pub fn read(
+    path: &Path,
+    format: Option<ImportFormat>,
+    encoding: Option<Encoding>,
+    csv_options: &csv::CsvOptions,
+) -> Result<Statement, Error> {
+    let format = format
+        .or_else(|| ImportFormat::detect(path))
+        .ok_or_else(|| {
+            Error::Import(
+                path.to_path_buf(),
+                "unknown format, please specify it with --format".to_string(),
+            )
+        })?;
+
+    debug!("reading {} as {:?}", path.to_string_lossy(), format);
+
+    let contents = std::fs::read(path)?;
+    let contents = encoding
+        .or_else(|| match format {
+            ImportFormat::Ofx => ofx::encoding(&contents),
+            _ => None,
+        })
+        .unwrap_or(Encoding::Utf8)
+        .decode(&contents)
+        .map_err(|err| Error::Import(path.to_path_buf(), err))?;
+
+    match format {
+        ImportFormat::Csv => csv::parse(&contents, csv_options),
+        ImportFormat::Camt053 => camt053::parse(&contents),
+        ImportFormat::Mt940 => mt940::parse(&contents),
+        ImportFormat::Ofx => ofx::parse(&contents),
+    }
+    .map_err(|err| Error::Import(path.to_path_buf(), err))
+}
+
+/// Parse an account name such as `Assets:Bank:Checking`.
+pub fn parse_account(name: &str) -> Result<Account<'static>, Error> {
+    let mut parts = name.split(':');
+
+    let ty = match parts.next() {
+        Some("Assets") => AccountType::Assets,
+        Some("Liabilities") => AccountType::Liabilities,
+        Some("Equity") => AccountType::Equity,
+        Some("Income") => AccountType::Income,
+        Some("Expenses") => AccountType::Expenses,
+        _ => return Err(Error::InvalidAccount(name.to_string())),
+    };
+
+    Ok(Account {
+        ty,
+        parts: parts.map(|part| Cow::Owned(part.to_string())).collect(),
+    })
+}
+
+fn date(date: NaiveDate) -> Date<'static> {
+    Date::from_string_unchecked(date.format("%Y-%m-%d").to_string())
+}
+
+/// Turn the lines of the statement into transactions between `account` and the
+/// account chosen by `rules`, followed by an assertion of the closing balance.
+/// The currency stated by the export takes precedence over `currency`.
+pub fn directives(
+    statement: &Statement,
+    account: &Account<'static>,
+    currency: &str,
+    rules: &Rules,
+) -> Vec<Directive<'static>> {
+    let currency = statement.currency.as_deref().unwrap_or(currency);
+    let mut entries: Vec<_> = statement.entries.iter().collect();
+    entries.sort_by_key(|entry| entry.date);
+
+    let mut directives: Vec<_> = entries
+        .into_iter()
+        .map(|entry| {
+            let postings = vec![
+                Posting::builder()
+                    .account(account.clone())
+                    .units(IncompleteAmount {
+                        num: Some(entry.amount),
+                        currency: Some(Cow::Owned(currency.to_string())),
+                    })
+                    .build(),
+                // The counter-posting is elided, like when booking by hand.
+                Posting::builder()
+                    .account(rules.categorize(&entry.text).clone())
+                    .units(IncompleteAmount {
+                        num: None,
+                        currency: None,
+                    })
+                    .build(),
+            ];
+
+            let mut meta = HashMap::new();
+            if let Some(reference) = &entry.reference {
+                meta.insert(
+                    Cow::Borrowed(reference.key),
+                    MetaValue::Text(Cow::Owned(reference.value.clone())),
+                );
+            }
+
+            Directive::Transaction(
+                Transaction::builder()
+                    .date(date(entry.date))
+                    .payee(Some(Cow::Owned(entry.text.clone())))
+                    .narration(Cow::Borrowed(""))
+                    .postings(postings)
+                    .meta(meta)
+                    .build(),
+            )
+        })
+        .collect();
+
+    // Balance assertions apply to the beginning of the day, so the closing
+    // balance is asserted on the following day.
+    if let Some(closing) = &statement.closing_balance {
+        if let Some(next_day) = closing.date.checked_add_days(Days::new(1)) {
+            directives.push(Directive::Balance(
+                Balance::builder()
+                    .date(date(next_day))
+                    .account(account.clone())
+                    .amount(Amount {
+                        num: closing.amount,
+                        currency: Cow::Owned(currency.to_string()),
+                    })
+                    .build(),
+            ));
+        }
+    }
+
+    directives
+}
+
+/// Metadata keys holding the identifiers assigned by banks.
+const REFERENCE_KEYS: [&str; 2] = ["reference", "fitid"];
+
+/// Bank references of a transaction.
+fn references<'b>(txn: &'b Transaction) -> impl Iterator<Item = (&'b str, &'b str)> {
+    txn.meta.iter().filter_map(|(key, value)| match value {
+        MetaValue::Text(value) if REFERENCE_KEYS.contains(&key.as_ref()) => {
+            Some((key.as_ref(), value.as_ref()))
+        }
+        _ => None,
+    })
+}
+
+/// Leave out the imported transactions already found in the ledger. A transaction
+/// is considered present if the ledger holds one posting to the imported account
+/// and carrying the same bank reference, or failing that, one with the same date
+/// and payee, and an identical posting to the imported account, regardless of how
+/// it was categorized.
+pub fn skip_present<'a>(
+    existing: &[Sourced<'a, Directive<'a>>],
+    imported: Vec<Directive<'static>>,
+) -> Vec<Directive<'static>> {
+    // Counted, so a purchase made twice on the same day is only skipped once per booking.
+    let mut present = HashMap::new();
+    // References are only unique per bank, so they are kept per account.
+    let mut references = HashSet::new();
+    for directive in existing {
+        if let Directive::Transaction(txn) = &directive.inner {
+            for posting in &txn.postings {
+                for (key, value) in self::references(txn) {
+                    references.insert((account_name(&posting.account), key, value));
+                }
+            }
+
+            for posting in &txn.postings {
+                *present
+                    .entry((&txn.date, &txn.payee, posting.fingerprint()))
+                    .or_insert(0usize) += 1;
+            }
+        }
+    }
+
+    let keep: Vec<_> = imported
+        .iter()
+        .map(|directive| match directive {
+            // The first posting is the one to the imported account.
+            Directive::Transaction(txn)
+                if self::references(txn).any(|(key, value)| {
+                    references.contains(&(account_name(&txn.postings[0].account), key, value))
+                }) =>
+            {
+                false
+            }
+            Directive::Transaction(txn) => {
+                let key = (&txn.date, &txn.payee, txn.postings[0].fingerprint());
+                match present.get_mut(&key) {
+                    Some(count) if *count > 0 => {
+                        *count -= 1;
+                        false
+                    }
+                    _ => true,
+                }
+            }
+            Directive::Balance(balance) => !existing.iter().any(|directive| {
+                matches!(&directive.inner, Directive::Balance(other)
+                    if other.date == balance.date
+                        && other.account == balance.account
+                        && other.amount == balance.amount)
+            }),
+            _ => true,
+        })
+        .collect();
+
+    let total = imported.len();
+    let imported: Vec<_> = imported
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(directive, keep)| keep.then_some(directive))
+        .collect();
+
+    debug!(
+        "skipped {} of {} imported directives already in the ledger",
+        total - imported.len(),
+        total
+    );
+
+    imported
+}
+
+/// Render the directives as beancount source, separated by blank lines.
+pub fn render(directives: &[Directive]) -> Result<String, Error> {
+    let mut rendered = Vec::new();
+
+    for directive in directives {
+        if !rendered.is_empty() {
+            rendered.push(b'\n');
+        }
+
+        let ledger = beancount_core::Ledger {
+            directives: vec![directive.clone()],
+        };
+        beancount_render::render(&mut rendered, &ledger)
+            .map_err(|err| Error::Render(err.to_string()))?;
+    }
+
+    Ok(String::from_utf8_lossy(&rendered).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::{
+        directives, parse_account, rules::Rules, skip_present, Encoding, Entry, Reference,
+        Statement,
+    };
+    use crate::inline_ledger;
+
+    #[test]
+    fn test_skip_present() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 * "Example Payee" ""
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Office
+
+        2000-01-02 * "Example Shop" ""
+            reference: "R1"
+            invoice: "R3"
+            Assets:Bank:Account  -100 DKK
+            Expenses:Office
+
+        2000-01-02 * "Example Shop" ""
+            reference: "R2"
+            Assets:Bank:Savings  -100 DKK
+            Expenses:Office
+        "#
+        );
+
+        let entry = |text: &str| Entry {
+            date: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            text: text.to_string(),
+            amount: Decimal::new(-1500, 0),
+            reference: None,
+        };
+
+        let referenced = |value: &str| Entry {
+            date: NaiveDate::from_ymd_opt(2000, 1, 3).unwrap(),
+            reference: Some(Reference::bank(value)),
+            ..entry("Example Shop")
+        };
+
+        let statement = Statement {
+            entries: vec![
+                entry("Example Payee"),
+                entry("Example Payee"),
+                entry("Other Payee"),
+                referenced("R1"),
+                referenced("R2"),
+                referenced("R3"),
+            ],
+            ..Default::default()
+        };
+
+        let account = parse_account("Assets:Bank:Account").unwrap();
+        let rules = Rules::new(parse_account("Expenses:Uncategorized").unwrap());
+        let imported = directives(&statement, &account, "DKK", &rules);
+        assert_eq!(imported.len(), 6);
+
+        // The booking in the ledger accounts for only one of the two identical lines,
+        // and only references of transactions of the imported account count.
+        let imported = skip_present(&ledger.directives(), imported);
+        assert_eq!(imported.len(), 4);
+    }
+
+    #[test]
+    fn test_decode() {
+        let bytes = b"Bager \xc6bler \x80 5";
+        assert!(Encoding::Utf8.decode(bytes).is_err());
+        assert_eq!(
+            Encoding::Latin1.decode(bytes).unwrap(),
+            "Bager Æbler \u{80} 5"
+        );
+        assert_eq!(Encoding::Cp1252.decode(bytes).unwrap(), "Bager Æbler € 5");
+        assert_eq!(Encoding::Utf8.decode("Æbler".as_bytes()).unwrap(), "Æbler");
+    }
+}
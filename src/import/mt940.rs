@@ -0,0 +1,218 @@
+use chrono::{Datelike, NaiveDate};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{csv::parse_amount, ClosingBalance, Entry, Reference, Statement};
+
+/// Value date, optional entry date, debit/credit mark, optional funds code, amount,
+/// transaction type, the reference of the account owner and that of the bank.
+static STATEMENT_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(\d{6})(\d{4})?(RC|RD|C|D)[A-Z]?(\d[\d,]*)[A-Z][A-Z0-9]{3}([^/]*)(?://(.*))?$")
+        .unwrap()
+});
+
+/// Debit/credit mark, date, currency and amount of a balance.
+static BALANCE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(C|D)(\d{6})([A-Z]{3})(\d[\d,]*)$").unwrap());
+
+/// Tags split over several lines are joined, every line not starting a new tag
+/// continuing the previous one.
+fn fields(contents: &str) -> Vec<(&str, Vec<&str>)> {
+    let mut fields: Vec<(&str, Vec<&str>)> = Vec::new();
+
+    for line in contents.lines().map(str::trim_end) {
+        let tag = line.strip_prefix(':').and_then(|line| line.split_once(':'));
+
+        if let Some((tag, value)) = tag {
+            fields.push((tag, vec![value]));
+        } else if line == "-" {
+            // The closing dash of each message ends the last field.
+            fields.push((line, Vec::new()));
+        } else if let Some((_, lines)) = fields.last_mut() {
+            lines.push(line);
+        }
+    }
+
+    fields
+}
+
+fn date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%y%m%d").ok()
+}
+
+/// Entry date given as month and day, in the year closest to the value date, since
+/// an entry booked around new year may be valued in the year before or after.
+fn entry_date(value_date: NaiveDate, month_day: &str) -> Option<NaiveDate> {
+    let month = month_day.get(..2)?.parse().ok()?;
+    let day = month_day.get(2..)?.parse().ok()?;
+
+    [
+        value_date.year() - 1,
+        value_date.year(),
+        value_date.year() + 1,
+    ]
+    .into_iter()
+    .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+    .min_by_key(|date| (*date - value_date).num_days().abs())
+}
+
+fn balance(value: &str) -> Option<(ClosingBalance, &str)> {
+    let captures = BALANCE.captures(value.trim())?;
+    let amount = parse_amount(&captures[4], true)?;
+
+    Some((
+        ClosingBalance {
+            date: date(&captures[2])?,
+            amount: if &captures[1] == "D" { -amount } else { amount },
+        },
+        captures.get(3)?.as_str(),
+    ))
+}
+
+/// Structured information, as used by German banks, consists of a business code
+/// followed by subfields such as `?20` to `?29` for the purpose, and `?32` and
+/// `?33` for the name of the other party. Anything else is used as is.
+fn description(lines: &[&str]) -> String {
+    let joined = lines.concat();
+
+    if !joined.get(3..4).is_some_and(|separator| separator == "?") {
+        return lines.join(" ").trim().to_string();
+    }
+
+    let subfields: Vec<_> = joined[4..]
+        .split('?')
+        .filter_map(|subfield| Some((subfield.get(..2)?, subfield.get(2..)?)))
+        .collect();
+
+    let collect = |codes: &[&str]| {
+        subfields
+            .iter()
+            .filter(|(code, _)| codes.contains(code))
+            .map(|(_, text)| *text)
+            .collect::<String>()
+    };
+
+    let name = collect(&["32", "33"]);
+    if name.trim().is_empty() {
+        collect(&["20", "21", "22", "23", "24", "25", "26", "27", "28", "29"])
+            .trim()
+            .to_string()
+    } else {
+        name.trim().to_string()
+    }
+}
+
+pub fn parse(contents: &str) -> Result<Statement, String> {
+    let mut statement = Statement::default();
+
+    for (tag, lines) in fields(contents) {
+        let value = lines.first().copied().unwrap_or_default();
+
+        match tag {
+            "61" => {
+                let captures = STATEMENT_LINE
+                    .captures(value)
+                    .ok_or_else(|| format!("invalid statement line {}", value))?;
+
+                let amount = parse_amount(&captures[4], true)
+                    .ok_or_else(|| format!("invalid amount {}", &captures[4]))?;
+
+                // Reversals of credits are debits, and vice versa.
+                let amount = match &captures[3] {
+                    "D" | "RC" => -amount,
+                    _ => amount,
+                };
+
+                // The reference of the account owner is chosen by whoever made the
+                // payment, and often repeats, so only that of the bank is kept.
+                let reference = captures
+                    .get(6)
+                    .map(|reference| reference.as_str().trim())
+                    .filter(|reference| !reference.is_empty());
+
+                // Entries are dated by when they were booked, like the closing
+                // balance, falling back to the value date.
+                let value_date =
+                    date(&captures[1]).ok_or_else(|| format!("invalid date in {}", value))?;
+                let date = match captures.get(2) {
+                    Some(month_day) => entry_date(value_date, month_day.as_str())
+                        .ok_or_else(|| format!("invalid entry date in {}", value))?,
+                    None => value_date,
+                };
+
+                // Supplementary details on the following line describe the entry,
+                // which is overridden by the information in a subsequent `:86:`.
+                statement.entries.push(Entry {
+                    date,
+                    text: lines[1..].join(" ").trim().to_string(),
+                    amount,
+                    reference: reference.map(Reference::bank),
+                });
+            }
+            "86" => {
+                if let Some(entry) = statement.entries.last_mut() {
+                    let text = description(&lines);
+                    if !text.is_empty() {
+                        entry.text = text;
+                    }
+                }
+            }
+            "62F" => {
+                let (closing, currency) =
+                    balance(value).ok_or_else(|| format!("invalid closing balance {}", value))?;
+
+                statement.closing_balance = Some(closing);
+                statement.currency = Some(currency.to_string());
+            }
+            "60F" | "60M" if statement.currency.is_none() => {
+                statement.currency = balance(value).map(|(_, currency)| currency.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(statement)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::parse;
+
+    #[test]
+    fn test_parse_mt940() {
+        let contents = "\
+:20:STATEMENT
+:25:12345678/0000123456
+:28C:1/1
+:60F:C000101DKK10000,00
+:61:9912310103D1500,00NTRFNONREF//2000010312345
+:86:Example Payee
+:61:0001050104C250,NMSCREFUND-17
+:86:166?00GUTSCHRIFT?20Refund of?21 order 17?32Example Shop
+:62F:C000131DKK8750,00
+-";
+
+        let statement = parse(contents).unwrap();
+        assert_eq!(statement.currency.as_deref(), Some("DKK"));
+        assert_eq!(statement.entries.len(), 2);
+
+        let payment = &statement.entries[0];
+        assert_eq!(payment.text, "Example Payee");
+        assert_eq!(payment.date, NaiveDate::from_ymd_opt(2000, 1, 3).unwrap());
+        assert_eq!(payment.amount, Decimal::new(-1500, 0));
+        assert_eq!(payment.reference.as_ref().unwrap().value, "2000010312345");
+
+        let refund = &statement.entries[1];
+        assert_eq!(refund.text, "Example Shop");
+        assert_eq!(refund.date, NaiveDate::from_ymd_opt(2000, 1, 4).unwrap());
+        assert_eq!(refund.amount, Decimal::new(250, 0));
+        assert_eq!(refund.reference, None);
+
+        let closing = statement.closing_balance.unwrap();
+        assert_eq!(closing.date, NaiveDate::from_ymd_opt(2000, 1, 31).unwrap());
+        assert_eq!(closing.amount, Decimal::new(8750, 0));
+    }
+}
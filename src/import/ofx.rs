@@ -0,0 +1,207 @@
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use super::{ClosingBalance, Encoding, Entry, Reference, Statement};
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Contents of each aggregate named `tag`. Aggregates are closed in both the
+/// SGML based OFX 1.x and the XML based OFX 2.x.
+fn aggregates<'a>(contents: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+
+    let mut aggregates = Vec::new();
+    let mut rest = contents;
+
+    while let Some(start) = rest.find(&open) {
+        let inner = &rest[start + open.len()..];
+        let end = inner.find(&close).unwrap_or(inner.len());
+
+        aggregates.push(&inner[..end]);
+        rest = &inner[end..];
+    }
+
+    aggregates
+}
+
+/// Value of the first element named `tag`. Elements holding values are not
+/// closed in OFX 1.x, so the value runs until the next tag in either version.
+fn value(aggregate: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let start = aggregate.find(&open)? + open.len();
+    let value = aggregate[start..].split('<').next()?.trim();
+
+    (!value.is_empty()).then(|| unescape(value))
+}
+
+/// Dates are written as `YYYYMMDD`, optionally followed by a time and a time zone.
+fn date(aggregate: &str, tag: &str) -> Option<NaiveDate> {
+    let value = value(aggregate, tag)?;
+    NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+}
+
+fn amount(aggregate: &str, tag: &str) -> Option<Decimal> {
+    Decimal::from_str(&value(aggregate, tag)?.replace(',', ".")).ok()
+}
+
+fn entry(transaction: &str) -> Result<Entry, String> {
+    let fitid = value(transaction, "FITID");
+    let error = |field: &str| {
+        format!(
+            "transaction {}: missing or invalid {}",
+            fitid.as_deref().unwrap_or("without FITID"),
+            field
+        )
+    };
+
+    Ok(Entry {
+        date: date(transaction, "DTPOSTED").ok_or_else(|| error("DTPOSTED"))?,
+        text: value(transaction, "NAME")
+            .or_else(|| value(transaction, "MEMO"))
+            .unwrap_or_default(),
+        amount: amount(transaction, "TRNAMT").ok_or_else(|| error("TRNAMT"))?,
+        reference: fitid.clone().map(|fitid| Reference {
+            key: "fitid",
+            value: fitid,
+        }),
+    })
+}
+
+/// Encoding declared by the header of an OFX 1.x export, such as `CHARSET:1252`.
+/// The header precedes the first tag, and is plain ASCII.
+pub fn encoding(contents: &[u8]) -> Option<Encoding> {
+    let end = contents
+        .iter()
+        .position(|byte| *byte == b'<')
+        .unwrap_or(contents.len());
+    let header = String::from_utf8_lossy(&contents[..end]);
+
+    let field = |name: &str| {
+        header
+            .lines()
+            .filter_map(|line| line.trim().split_once(':'))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
+    };
+
+    match (field("ENCODING").as_deref(), field("CHARSET").as_deref()) {
+        (Some("UTF-8"), _) => Some(Encoding::Utf8),
+        (_, Some("1252")) => Some(Encoding::Cp1252),
+        (_, Some("ISO-8859-1" | "8859-1")) => Some(Encoding::Latin1),
+        _ => None,
+    }
+}
+
+pub fn parse(contents: &str) -> Result<Statement, String> {
+    if !contents.contains("<OFX>") {
+        return Err("no OFX document found".to_string());
+    }
+
+    let entries = aggregates(contents, "STMTTRN")
+        .into_iter()
+        .map(entry)
+        .collect::<Result<_, _>>()?;
+
+    let closing_balance = aggregates(contents, "LEDGERBAL")
+        .first()
+        .and_then(|balance| {
+            Some(ClosingBalance {
+                date: date(balance, "DTASOF")?,
+                amount: amount(balance, "BALAMT")?,
+            })
+        });
+
+    Ok(Statement {
+        currency: value(contents, "CURDEF"),
+        entries,
+        closing_balance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::{encoding, parse};
+    use crate::import::Encoding;
+
+    #[test]
+    fn test_parse_ofx() {
+        let sgml = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS>
+<CURDEF>DKK
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20000103120000.000[+1:CET]
+<TRNAMT>-1500.00
+<FITID>2000010312345
+<NAME>Example &amp; Payee
+</STMTTRN>
+</BANKTRANLIST>
+<LEDGERBAL>
+<BALAMT>-1500.00
+<DTASOF>20000131
+</LEDGERBAL>
+</CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1>
+</OFX>";
+
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="220"?>
+<OFX><CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS>
+<CURDEF>DKK</CURDEF>
+<BANKTRANLIST>
+<STMTTRN><TRNTYPE>DEBIT</TRNTYPE><DTPOSTED>20000103</DTPOSTED><TRNAMT>-1500.00</TRNAMT><FITID>2000010312345</FITID><NAME>Example &amp; Payee</NAME></STMTTRN>
+</BANKTRANLIST>
+<LEDGERBAL><BALAMT>-1500.00</BALAMT><DTASOF>20000131</DTASOF></LEDGERBAL>
+</CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1></OFX>"#;
+
+        for contents in [sgml, xml] {
+            let statement = parse(contents).unwrap();
+            assert_eq!(statement.currency.as_deref(), Some("DKK"));
+            assert_eq!(statement.entries.len(), 1);
+
+            let entry = &statement.entries[0];
+            assert_eq!(entry.date, NaiveDate::from_ymd_opt(2000, 1, 3).unwrap());
+            assert_eq!(entry.text, "Example & Payee");
+            assert_eq!(entry.amount, Decimal::new(-1500, 0));
+
+            let reference = entry.reference.as_ref().unwrap();
+            assert_eq!(
+                (reference.key, reference.value.as_str()),
+                ("fitid", "2000010312345")
+            );
+
+            let closing = statement.closing_balance.unwrap();
+            assert_eq!(closing.date, NaiveDate::from_ymd_opt(2000, 1, 31).unwrap());
+            assert_eq!(closing.amount, Decimal::new(-1500, 0));
+        }
+    }
+
+    #[test]
+    fn test_encoding() {
+        let header =
+            b"OFXHEADER:100\nDATA:OFXSGML\nENCODING:USASCII\nCHARSET:1252\n\n<OFX>\n<NAME>\xc6bler";
+        assert_eq!(encoding(header), Some(Encoding::Cp1252));
+        assert_eq!(
+            encoding(b"OFXHEADER:100\nENCODING:UTF-8\n\n<OFX>"),
+            Some(Encoding::Utf8)
+        );
+        assert_eq!(encoding(b"<?xml version=\"1.0\"?>\n<OFX>"), None);
+    }
+}
@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use beancount_core::Account;
+use regex::Regex;
+
+use crate::error::Error;
+
+use super::{classifier::Classifier, parse_account};
+
+/// Regular expressions matched against the text of each statement line, deciding
+/// which account its counter-posting is booked to. Rules are tried in order, and
+/// lines not matched by any of them are left to the classifier, if any, and
+/// otherwise booked to the fallback account.
+#[derive(Debug)]
+pub struct Rules {
+    rules: Vec<(Regex, Account<'static>)>,
+    /// Classifier along with the confidence its suggestions need to be used.
+    classifier: Option<(Classifier, f64)>,
+    fallback: Account<'static>,
+}
+
+impl Rules {
+    pub fn new(fallback: Account<'static>) -> Self {
+        Rules {
+            rules: Vec::new(),
+            classifier: None,
+            fallback,
+        }
+    }
+
+    /// Fall back to the suggestions of `classifier` for lines not matched by
+    /// any rule, if it is at least `confidence` sure of them.
+    pub fn with_classifier(mut self, classifier: Classifier, confidence: f64) -> Self {
+        self.classifier = Some((classifier, confidence));
+        self
+    }
+
+    /// Parse rules written one per line, as an account followed by a pattern:
+    ///
+    /// ```text
+    /// ; Office supplies
+    /// Expenses:Office  (?i)staples|office depot
+    /// ```
+    pub fn parse(contents: &str, fallback: Account<'static>) -> Result<Self, String> {
+        let mut rules = Rules::new(fallback);
+
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            let Some((account, pattern)) = line.split_once(char::is_whitespace) else {
+                return Err(format!(
+                    "line {}: expected an account and a pattern",
+                    number + 1
+                ));
+            };
+
+            let account =
+                parse_account(account).map_err(|err| format!("line {}: {}", number + 1, err))?;
+            let pattern = Regex::new(pattern.trim())
+                .map_err(|err| format!("line {}: {}", number + 1, err))?;
+
+            rules.rules.push((pattern, account));
+        }
+
+        Ok(rules)
+    }
+
+    pub fn load(path: &Path, fallback: Account<'static>) -> Result<Self, Error> {
+        let contents = std::fs::read_to_string(path)?;
+        Rules::parse(&contents, fallback).map_err(|err| Error::Import(path.to_path_buf(), err))
+    }
+
+    /// Account the counter-posting of a line with this text is booked to.
+    pub fn categorize(&self, text: &str) -> &Account<'static> {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(text))
+            .map(|(_, account)| account)
+            .or_else(|| {
+                let (classifier, confidence) = self.classifier.as_ref()?;
+                let (account, probability) = classifier.classify(text)?;
+                (probability >= *confidence).then_some(account)
+            })
+            .unwrap_or(&self.fallback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rules;
+    use crate::import::{classifier::Classifier, parse_account};
+
+    #[test]
+    fn test_categorize() {
+        let rules = Rules::parse(
+            "; Office supplies\nExpenses:Office  (?i)staples\n\nExpenses:Travel  ^DSB\n",
+            parse_account("Expenses:Uncategorized").unwrap(),
+        )
+        .unwrap();
+
+        let categorize = |text| rules.categorize(text).parts.join(":");
+        assert_eq!(categorize("STAPLES COPENHAGEN"), "Office");
+        assert_eq!(categorize("DSB 7-Eleven"), "Travel");
+        assert_eq!(categorize("Netto"), "Uncategorized");
+    }
+
+    #[test]
+    fn test_unknown_payee_falls_back() {
+        // With a single learned account, the classifier would be certain of
+        // anything it has no words in common with.
+        let mut classifier = Classifier::default();
+        classifier.train("Netto", &parse_account("Expenses:Food").unwrap());
+
+        let rules = Rules::new(parse_account("Expenses:Uncategorized").unwrap())
+            .with_classifier(classifier, 0.8);
+
+        let categorize = |text| rules.categorize(text).parts.join(":");
+        assert_eq!(categorize("NETTO FOTEX 1234"), "Food");
+        assert_eq!(categorize("Kiosk 7"), "Uncategorized");
+    }
+}
@@ -4,11 +4,17 @@ use std::{
     hash::Hash,
     ops::Deref,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
 };
 
 use beancount_core::Directive;
 use beancount_parser::parse;
 use colored::Colorize;
+use log::{debug, info};
+use once_cell::sync::OnceCell;
+use rayon::prelude::*;
+use self_cell::self_cell;
 
 use crate::{error::Error, location::Location};
 
@@ -25,15 +31,13 @@ pub enum LedgerSource {
 #[macro_export]
 macro_rules! inline_ledger {
     ( $source:literal ) => {
-        $crate::ledger::Ledger {
-            files: vec![$crate::ledger::LedgerFile {
-                source: $crate::ledger::LedgerSource::Code {
-                    filename: file!(),
-                    line_offset: line!() + 1,
-                },
-                text_contents: unindent::unindent($source),
-            }],
-        }
+        $crate::ledger::Ledger::from_files(vec![$crate::ledger::LedgerFile {
+            source: $crate::ledger::LedgerSource::Code {
+                filename: file!(),
+                line_offset: line!() + 1,
+            },
+            text_contents: unindent::unindent($source),
+        }])
     };
 }
 
@@ -178,14 +182,95 @@ impl_downcast!(Price);
 impl_downcast!(Query);
 impl_downcast!(Transaction);
 
+/// Directives of a single file, or the reason it could not be parsed.
+type Parsed<'a> = Result<Vec<Sourced<'a, Directive<'a>>>, Error>;
+
+self_cell!(
+    /// A file along with the directives parsed from it, which borrow from the file.
+    struct ParsedFile {
+        owner: Arc<LedgerFile>,
+
+        #[covariant]
+        dependent: Parsed,
+    }
+);
+
+type Directives<'a> = Vec<Sourced<'a, Directive<'a>>>;
+
+self_cell!(
+    /// Parsed files along with their directives combined into a single list.
+    struct Combined {
+        owner: Vec<Arc<ParsedFile>>,
+
+        #[covariant]
+        dependent: Directives,
+    }
+);
+
 /// A Ledger is a complete view of an entire directory structure potentially
 /// containing multiple beancount and csv files.
-#[derive(Debug)]
+///
+/// Files are parsed on first use, and their directives kept until the file is
+/// replaced.
 pub struct Ledger {
-    pub files: Vec<LedgerFile>,
+    files: Vec<Arc<LedgerFile>>,
+    /// Directives of each file, in the same order as `files`.
+    parsed: Vec<OnceCell<Arc<ParsedFile>>>,
+    /// Directives of every file, in the same order as `files`.
+    directives: OnceCell<Combined>,
+}
+
+impl Debug for Ledger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ledger")
+            .field("files", &self.files)
+            .finish()
+    }
 }
 
 impl Ledger {
+    pub fn from_files(files: Vec<LedgerFile>) -> Self {
+        Ledger {
+            parsed: files.iter().map(|_| OnceCell::new()).collect(),
+            directives: OnceCell::new(),
+            files: files.into_iter().map(Arc::new).collect(),
+        }
+    }
+
+    pub fn files(&self) -> impl ExactSizeIterator<Item = &LedgerFile> + Clone {
+        self.files.iter().map(|file| file.as_ref())
+    }
+
+    /// Add a file to the ledger, replacing any file of the same name, and
+    /// forgetting the directives parsed from it.
+    pub fn insert(&mut self, file: LedgerFile) {
+        self.directives.take();
+
+        match self
+            .files
+            .iter()
+            .position(|existing| existing.source == file.source)
+        {
+            Some(index) => {
+                self.parsed[index].take();
+                self.files[index] = Arc::new(file);
+            }
+            None => {
+                self.files.push(Arc::new(file));
+                self.parsed.push(OnceCell::new());
+            }
+        }
+    }
+
+    /// Remove the file read from `path`, if it is part of the ledger.
+    pub fn remove(&mut self, path: &Path) -> Option<Arc<LedgerFile>> {
+        let index = self.files.iter().position(|file| file.filename() == path)?;
+
+        self.directives.take();
+        self.parsed.remove(index);
+        Some(self.files.remove(index))
+    }
+
     fn list_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
         fn visit_files_internal(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
             if dir.is_dir() {
@@ -213,30 +298,123 @@ impl Ledger {
         for file in Self::list_files(path.as_ref())? {
             if let Some(ext) = file.extension() {
                 if ext == "beancount" {
-                    // Parsing fails if there are no trailing newlines.
-                    let source = {
-                        let mut source = std::fs::read_to_string(&file)?;
-                        source.push('\n');
-                        source
-                    };
-
-                    ledgers.push(LedgerFile {
-                        source: file.into(),
-                        text_contents: source,
-                    });
+                    ledgers.push(LedgerFile::from_file(file)?);
                 }
             }
         }
 
-        Ok(Ledger { files: ledgers })
+        Ok(Ledger::from_files(ledgers))
+    }
+
+    /// Parse the files which have not been parsed yet, in parallel.
+    fn parse(&self) {
+        let pending: Vec<_> = self
+            .files
+            .iter()
+            .zip(&self.parsed)
+            .filter(|(_, parsed)| parsed.get().is_none())
+            .collect();
+
+        if pending.is_empty() {
+            return;
+        }
+
+        let start = Instant::now();
+        let files: Vec<_> = pending.iter().map(|(file, _)| Arc::clone(file)).collect();
+        let results: Vec<_> = files
+            .into_par_iter()
+            .map(|file| ParsedFile::new(file, |file| file.try_directives()))
+            .collect();
+
+        for ((_, parsed), result) in pending.iter().zip(results) {
+            let _ = parsed.set(Arc::new(result));
+        }
+
+        info!("parsed {} files in {:.2?}", pending.len(), start.elapsed());
+    }
+
+    /// Each file of the ledger, along with its directives, or the reason it
+    /// could not be parsed.
+    pub fn parsed_files(
+        &self,
+    ) -> impl Iterator<Item = (&LedgerFile, Result<&[Sourced<'_, Directive<'_>>], &Error>)> {
+        self.parse();
+
+        self.parsed.iter().map(|parsed| {
+            let parsed = parsed.get().expect("every file has been parsed");
+            (
+                parsed.borrow_owner().as_ref(),
+                parsed.borrow_dependent().as_ref().map(Vec::as_slice),
+            )
+        })
+    }
+
+    /// Directives of the file read from `path`, parsing only that file if it
+    /// has not been parsed yet.
+    pub fn parsed(&self, path: &Path) -> Option<Result<&[Sourced<'_, Directive<'_>>], &Error>> {
+        let index = self.files.iter().position(|file| file.filename() == path)?;
+        let parsed = self.parsed[index].get_or_init(|| {
+            Arc::new(ParsedFile::new(Arc::clone(&self.files[index]), |file| {
+                file.try_directives()
+            }))
+        });
+
+        Some(parsed.borrow_dependent().as_ref().map(Vec::as_slice))
     }
 
-    pub fn directives(&self) -> Vec<Sourced<'_, Directive<'_>>> {
-        self.files.iter().flat_map(LedgerFile::directives).collect()
+    /// Directives of every file of the ledger. Files are parsed in parallel on
+    /// first use, and the result is shared by later calls.
+    ///
+    /// Panics if any file fails to parse.
+    pub fn directives(&self) -> &[Sourced<'_, Directive<'_>>] {
+        let combined = self.directives.get_or_init(|| {
+            self.parse();
+
+            let files: Vec<_> = self
+                .parsed
+                .iter()
+                .map(|parsed| Arc::clone(parsed.get().expect("every file has been parsed")))
+                .collect();
+
+            Combined::new(files, |files| {
+                let directives: Vec<_> = files
+                    .iter()
+                    .flat_map(|file| match file.borrow_dependent() {
+                        Ok(directives) => directives.iter().cloned(),
+                        Err(err) => panic!(
+                            "{}: {}",
+                            file.borrow_owner().filename().to_string_lossy(),
+                            err
+                        ),
+                    })
+                    .collect();
+
+                debug!(
+                    "combined {} directives from {} files",
+                    directives.len(),
+                    files.len()
+                );
+
+                directives
+            })
+        });
+
+        combined.borrow_dependent()
     }
 }
 
 impl LedgerFile {
+    pub fn from_file(path: PathBuf) -> Result<Self, Error> {
+        // Parsing fails if there are no trailing newlines.
+        let mut source = std::fs::read_to_string(&path)?;
+        source.push('\n');
+
+        Ok(LedgerFile {
+            source: path.into(),
+            text_contents: source,
+        })
+    }
+
     pub fn filename(&self) -> PathBuf {
         self.source.filename()
     }
@@ -245,17 +423,29 @@ impl LedgerFile {
         self.source.line_offset()
     }
 
-    fn directives(&self) -> Vec<Sourced<'_, Directive<'_>>> {
-        let ledger = parse(&self.text_contents)
-            .map_err(|e| Error::Ledger(self.source.filename(), e))
-            .unwrap();
+    /// Contents of the file as they were read from disk, without the
+    /// trailing newline appended to appease the parser.
+    pub fn original_contents(&self) -> &str {
+        match &self.source {
+            LedgerSource::File(_) => self
+                .text_contents
+                .strip_suffix('\n')
+                .unwrap_or(&self.text_contents),
+            #[cfg(test)]
+            LedgerSource::Code { .. } => &self.text_contents,
+        }
+    }
+
+    pub fn try_directives(&self) -> Result<Vec<Sourced<'_, Directive<'_>>>, Error> {
+        let ledger =
+            parse(&self.text_contents).map_err(|e| Error::Ledger(self.source.filename(), e))?;
 
         // We use this hashmap to keep track of each occurrence of a directive.
         // In the case that an identical directive occurs multiple times, such as duplicated
         // entries, we'll need to count each occurrence and map them to a single Directive
         // instance.
         let mut occurrences = HashMap::new();
-        ledger
+        Ok(ledger
             .directives
             .into_iter()
             .map(|directive| {
@@ -327,6 +517,6 @@ impl LedgerFile {
                     );
                 }
             })
-            .collect()
+            .collect())
     }
 }
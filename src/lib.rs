@@ -0,0 +1,23 @@
+pub mod amount;
+pub mod annual;
+pub mod appendix;
+pub mod balance;
+pub mod budget;
+pub mod cache;
+pub mod categorize;
+pub mod date;
+pub mod edit;
+pub mod error;
+pub mod format;
+pub mod import;
+pub mod ledger;
+pub mod lints;
+pub mod location;
+pub mod lsp;
+pub mod query;
+pub mod readable;
+pub mod reconcile;
+pub mod register;
+pub mod sort;
+pub mod trend;
+pub mod watch;
@@ -0,0 +1,201 @@
+use std::{collections::HashMap, fmt::Display, panic::catch_unwind, path::Path};
+
+use beancount_core::Directive;
+use colored::Colorize;
+use itertools::Itertools;
+use log::{debug, warn};
+use rust_decimal::Decimal;
+
+use crate::{
+    amount::totals,
+    appendix::{AppendixExtractor, IntoAppendices, TransactionWithAppendix},
+    ledger::Sourced,
+    location::Location,
+    readable::Payees,
+};
+
+use super::Lint;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AmountMismatch<'a> {
+    entry: TransactionWithAppendix<'a>,
+    amounts: Vec<String>,
+}
+
+impl<'a> AmountMismatch<'a> {
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        vec![self.entry.transaction.location.clone()]
+    }
+}
+
+impl<'a> From<AmountMismatch<'a>> for Lint<'a> {
+    fn from(amount_mismatch: AmountMismatch<'a>) -> Self {
+        Lint::AmountMismatch(amount_mismatch)
+    }
+}
+
+impl<'a> Display for AmountMismatch<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} amount {} of transaction {} does not appear in {}:",
+            "warning:".yellow().bold(),
+            self.amounts.join(" / ").bold(),
+            Payees::from(&self.entry.transaction),
+            self.entry.appendix.statement().green(),
+        )?;
+
+        writeln!(f, "{}", self.entry.transaction.location)
+    }
+}
+
+/// All the ways `amount` is likely to be written in a document, such as
+/// `1.500,00`, `1,500.00`, `1 500,00` and `1500.00`.
+fn written_forms(amount: Decimal) -> Vec<String> {
+    let amount = amount.abs().round_dp(2);
+    let integer = amount.trunc().to_string();
+    let cents = format!(
+        "{:0>2}",
+        (amount.fract() * Decimal::from(100)).trunc().to_string()
+    );
+
+    // Group the integer part into thousands, e.g. ["1", "500"]
+    let digits: Vec<char> = integer.chars().collect();
+    let groups: Vec<String> = digits
+        .rchunks(3)
+        .rev()
+        .map(|group| group.iter().collect())
+        .collect();
+
+    let mut forms = Vec::new();
+    for thousands in ["", ".", ",", " ", "'"] {
+        let integer = groups.join(thousands);
+        for decimal in [",", "."] {
+            if thousands != decimal {
+                forms.push(format!("{integer}{decimal}{cents}"));
+            }
+        }
+
+        // Whole amounts are frequently written without decimals.
+        if cents == "00" {
+            forms.push(integer);
+        }
+    }
+
+    forms.into_iter().unique().collect()
+}
+
+/// Check whether `amount` occurs anywhere in `text`, and isn't just part of a larger number.
+fn contains_amount(text: &str, amount: Decimal) -> bool {
+    written_forms(amount).iter().any(|form| {
+        text.match_indices(form.as_str()).any(|(index, _)| {
+            let before = text[..index].chars().next_back();
+            let after = text[index + form.len()..].chars().next();
+
+            let is_number = |c: Option<char>| c.is_some_and(|c| c.is_ascii_digit());
+            let is_separator = |c: Option<char>| c.is_some_and(|c| ",. '".contains(c));
+
+            // A separator next to another digit means the number is part of a larger one,
+            // such as the 500 in 1.500,00
+            let continued_before =
+                is_separator(before) && is_number(text[..index].chars().nth_back(1));
+            let continued_after =
+                is_separator(after) && is_number(text[index + form.len()..].chars().nth(1));
+
+            !is_number(before) && !is_number(after) && !continued_before && !continued_after
+        })
+    })
+}
+
+/// Extract the text layer of the pdf document, with all whitespace normalized to single spaces.
+fn extract_text(path: &Path) -> Option<String> {
+    debug!("extracting text from {}", path.to_string_lossy());
+
+    // The pdf parser is known to panic on some malformed documents.
+    let extracted = catch_unwind(|| pdf_extract::extract_text(path));
+
+    match extracted {
+        Ok(Ok(text)) => Some(text.split_whitespace().join(" ")),
+        Ok(Err(err)) => {
+            warn!(
+                "unable to extract text from {}: {}",
+                path.to_string_lossy(),
+                err
+            );
+            None
+        }
+        Err(_) => {
+            warn!("unable to extract text from {}", path.to_string_lossy());
+            None
+        }
+    }
+}
+
+pub fn find_amount_mismatches<'a, Extractor: AppendixExtractor<'a>>(
+    directives: &[Sourced<'a, Directive<'a>>],
+) -> Vec<Lint<'a>> {
+    debug!("checking for amounts missing from documents");
+
+    // Many transactions can refer to the same document, so only extract each one once.
+    let mut documents: HashMap<String, Option<String>> = HashMap::new();
+
+    directives
+        .iter()
+        .cloned()
+        .into_appendices::<Extractor>()
+        .into_iter()
+        .filter_map(|entry| {
+            let statement = entry.appendix.statement();
+            let path = Path::new(statement);
+
+            // Only pdf documents with a text layer can be checked, and documents
+            // which do not exist are reported by [`crate::lints::document_missing`]
+            if !statement.to_lowercase().ends_with(".pdf") || !path.exists() {
+                return None;
+            }
+
+            let text = documents
+                .entry(statement.to_string())
+                .or_insert_with(|| extract_text(path))
+                .as_ref()?;
+
+            let totals = totals(&entry.transaction);
+            if totals.is_empty() || totals.values().any(|amount| contains_amount(text, *amount)) {
+                return None;
+            }
+
+            let amounts = totals
+                .iter()
+                .map(|(currency, amount)| match currency {
+                    Some(currency) => format!("{} {}", amount.round_dp(2), currency),
+                    None => amount.round_dp(2).to_string(),
+                })
+                .collect();
+
+            Some(AmountMismatch { entry, amounts }.into())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::contains_amount;
+
+    #[test]
+    fn test_contains_amount() {
+        let text = "Faktura nr. 1234 Total DKK 1.500,00 heraf moms 300,00";
+
+        assert!(contains_amount(text, Decimal::from(1500)));
+        assert!(contains_amount(text, Decimal::from(-1500)));
+        assert!(contains_amount(text, Decimal::from(300)));
+        assert!(!contains_amount(text, Decimal::from(500)));
+        assert!(!contains_amount(text, Decimal::from(1501)));
+
+        let text = "Amount due: $1,234.50 (ref 99)";
+        assert!(contains_amount(text, Decimal::new(123450, 2)));
+        assert!(!contains_amount(text, Decimal::from(234)));
+        assert!(!contains_amount(text, Decimal::from(9)));
+    }
+}
@@ -0,0 +1,147 @@
+use std::fmt::Display;
+
+use beancount_core::Directive;
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use colored::Colorize;
+use log::debug;
+
+use crate::{
+    date::{directive_date, naive_date},
+    ledger::Sourced,
+    location::{Location, ToLocationSpan},
+};
+
+use super::Lint;
+
+/// Order in which the directives of each file are meant to be dated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Chronology {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl Chronology {
+    /// Whether `next` may follow `previous` in a file ordered this way.
+    pub fn in_order(&self, previous: NaiveDate, next: NaiveDate) -> bool {
+        match self {
+            Chronology::Ascending => previous <= next,
+            Chronology::Descending => previous >= next,
+        }
+    }
+}
+
+impl Display for Chronology {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Chronology::Ascending => write!(f, "ascending"),
+            Chronology::Descending => write!(f, "descending"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Hash, Eq)]
+pub struct OutOfOrder<'a> {
+    previous: (Location<'a>, NaiveDate),
+    entry: (Location<'a>, NaiveDate),
+    chronology: Chronology,
+}
+
+impl<'a> OutOfOrder<'a> {
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        vec![self.entry.0.clone()]
+    }
+}
+
+impl<'a> From<OutOfOrder<'a>> for Lint<'a> {
+    fn from(out_of_order: OutOfOrder<'a>) -> Self {
+        Lint::OutOfOrder(out_of_order)
+    }
+}
+
+impl<'a> Display for OutOfOrder<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} directive dated {} follows one dated {} in a file ordered by {} date:",
+            "warning:".yellow().bold(),
+            self.entry.1.to_string().bold().green(),
+            self.previous.1.to_string().bold().green(),
+            self.chronology,
+        )?;
+
+        for source in [self.previous.0.clone(), self.entry.0.clone()]
+            .into_iter()
+            .to_span(10)
+        {
+            writeln!(f, "{}", source)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Find directives which are dated out of order relative to the directive
+/// preceding them in the same file.
+pub fn find_out_of_order<'a>(
+    directives: &[Sourced<'a, Directive<'a>>],
+    chronology: Chronology,
+) -> Vec<Lint<'a>> {
+    debug!("checking for directives out of {} order", chronology);
+
+    let mut dated: Vec<_> = directives
+        .iter()
+        .filter_map(|directive| {
+            let date = naive_date(directive_date(&directive.inner)?)?;
+            Some((directive.location.clone(), date))
+        })
+        .collect();
+    dated.sort_by(|a, b| a.0.cmp(&b.0));
+
+    dated
+        .windows(2)
+        .filter(|pair| {
+            let (previous, next) = (&pair[0], &pair[1]);
+            previous.0.ledger() == next.0.ledger() && !chronology.in_order(previous.1, next.1)
+        })
+        .map(|pair| OutOfOrder {
+            previous: pair[0].clone(),
+            entry: pair[1].clone(),
+            chronology,
+        })
+        .map(Lint::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_out_of_order, Chronology};
+    use crate::inline_ledger;
+
+    #[test]
+    fn test_out_of_order() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 open Assets:Bank:Account
+
+        2000-01-03 * "Example Payee" ""
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Office
+
+        2000-01-02 * "Example Payee" ""
+            Assets:Bank:Account  -200 DKK
+            Expenses:Office
+
+        2000-01-04 balance Assets:Bank:Account  -1700 DKK
+        "#
+        );
+
+        let out_of_order = find_out_of_order(&ledger.directives(), Chronology::Ascending);
+        assert_eq!(out_of_order.len(), 1);
+        println!("{}", out_of_order[0]);
+
+        let out_of_order = find_out_of_order(&ledger.directives(), Chronology::Descending);
+        assert_eq!(out_of_order.len(), 2);
+    }
+}
@@ -7,6 +7,7 @@ use log::debug;
 use crate::{
     appendix::AppendixExtractor,
     ledger::{Downcast, Sourced},
+    location::Location,
     readable::Payees,
 };
 
@@ -23,6 +24,12 @@ impl<'a> From<Sourced<'a, Transaction<'a>>> for MissingDocument<'a> {
     }
 }
 
+impl<'a> MissingDocument<'a> {
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        vec![self.entry.location.clone()]
+    }
+}
+
 impl<'a> From<MissingDocument<'a>> for Lint<'a> {
     fn from(missing_document: MissingDocument<'a>) -> Self {
         Lint::MissingDocument(missing_document)
@@ -1,24 +1,46 @@
-use std::{collections::HashSet, fmt::Display};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
 
-use beancount_core::{Directive, Transaction};
+use beancount_core::{Account, AccountType, Directive, Transaction};
 use colored::Colorize;
 use log::debug;
+use rust_decimal::Decimal;
 
-use crate::{ledger::Sourced, lints::Lint, location::ToLocationSpan, readable::Payees};
+use crate::{
+    amount::resolved_postings,
+    date::day_number,
+    ledger::Sourced,
+    lints::Lint,
+    location::{Location, ToLocationSpan},
+    readable::Payees,
+};
 
 #[derive(Debug, PartialEq, Hash, Eq)]
 pub struct DoubleEntry<'a> {
-    entries: [Sourced<'a, Transaction<'a>>; 2],
+    entries: Vec<Sourced<'a, Transaction<'a>>>,
+    /// Likelihood that (at least two of) the transactions record the same transfer, in percent.
+    confidence: u8,
 }
 
 impl<'a> DoubleEntry<'a> {
-    fn from(entries: &[Sourced<'a, Transaction<'a>>]) -> Self {
-        let mut entries: [Sourced<'a, Transaction<'a>>; 2] =
-            [entries[0].clone(), entries[1].clone()];
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        self.entries
+            .iter()
+            .map(|entry| entry.location.clone())
+            .collect()
+    }
+
+    fn from(entries: &[Sourced<'a, Transaction<'a>>], confidence: u8) -> Self {
+        let mut entries = entries.to_vec();
 
         entries.sort_by(|a, b| a.location.cmp(&b.location));
 
-        DoubleEntry { entries }
+        DoubleEntry {
+            entries,
+            confidence,
+        }
     }
 }
 
@@ -32,17 +54,18 @@ impl<'a> Display for DoubleEntry<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(
             f,
-            "{} potential double entry transaction {}:",
+            "{} potential double entry transaction {} across {} transactions ({}% confidence):",
             "warning:".yellow().bold(),
-            Payees::from(&self.entries)
+            Payees::from(&self.entries),
+            self.entries.len(),
+            self.confidence
         )?;
 
-        for source in [
-            self.entries[0].location.clone(),
-            self.entries[1].location.clone(),
-        ]
-        .into_iter()
-        .to_span(10)
+        for source in self
+            .entries
+            .iter()
+            .map(|entry| entry.location.clone())
+            .to_span(10)
         {
             writeln!(f, "{}", source)?;
         }
@@ -51,76 +74,212 @@ impl<'a> Display for DoubleEntry<'a> {
     }
 }
 
-pub fn find_double_entries<'a>(directives: &[Sourced<'a, Directive<'a>>]) -> Vec<Lint<'a>> {
+/// Whether the account is one of our own, as opposed to an income or expense category.
+pub(super) fn is_own_account(account: &Account) -> bool {
+    matches!(account.ty, AccountType::Assets | AccountType::Liabilities)
+}
+
+/// A transfer of an amount from one of our own accounts to another.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct Transfer<'a> {
+    from: &'a Account<'a>,
+    to: &'a Account<'a>,
+    currency: Option<&'a str>,
+    amount: Decimal,
+}
+
+/// All the transfers between own accounts recorded by the transaction,
+/// regardless of the order in which the postings are written.
+fn transfers<'a>(transaction: &'a Transaction<'a>) -> Vec<Transfer<'a>> {
+    let postings: Vec<_> = resolved_postings(transaction)
+        .into_iter()
+        .filter(|posting| is_own_account(posting.account))
+        .collect();
+
+    postings
+        .iter()
+        .filter(|from| from.amount.is_sign_negative() && !from.amount.is_zero())
+        .flat_map(|from| {
+            postings
+                .iter()
+                .filter(move |to| {
+                    to.account != from.account
+                        && to.currency == from.currency
+                        && to.amount == -from.amount
+                })
+                .map(move |to| Transfer {
+                    from: from.account,
+                    to: to.account,
+                    currency: from.currency,
+                    amount: to.amount,
+                })
+        })
+        .collect()
+}
+
+/// Estimate how likely it is that two transactions recording the same transfer
+/// are in fact the same transfer entered twice, rather than two separate transfers.
+fn confidence(a: &Transaction, b: &Transaction, days_apart: u64) -> u8 {
+    let mut confidence: i64 = 100;
+
+    // Transfers usually post on the same day, or within a couple of days.
+    confidence -= 10 * days_apart as i64;
+
+    // Entering the same transfer from each account's statement typically means
+    // starting with a different account.
+    if a.postings.first().map(|p| &p.account) == b.postings.first().map(|p| &p.account) {
+        confidence -= 30;
+    }
+
+    // Postings to anything other than own accounts, such as fees, make it less
+    // likely to be a plain transfer.
+    for transaction in [a, b] {
+        if transaction
+            .postings
+            .iter()
+            .any(|posting| !is_own_account(&posting.account))
+        {
+            confidence -= 10;
+        }
+    }
+
+    confidence.clamp(0, 100) as u8
+}
+
+/// Find pairs of transactions which appear to record the same transfer between two
+/// of our own (asset or liability) accounts, within `window` days of each other.
+/// For example a transfer from one bank account to another, entered once from
+/// each account's statement.
+///
+/// Transactions are grouped by date, and within each date indexed by the transfers
+/// they record, so each transaction only has to be compared against the handful of
+/// transactions recording the same transfer within the window, rather than the
+/// entire ledger.
+pub fn find_double_entries<'a>(
+    directives: &[Sourced<'a, Directive<'a>>],
+    window: u32,
+) -> Vec<Lint<'a>> {
     debug!("checking for double entry transactions");
-    let mut double_entries = HashSet::new();
-
-    for first in directives.iter() {
-        if let Directive::Transaction(a) = &first.inner {
-            for second in directives.iter() {
-                if let Directive::Transaction(b) = &second.inner {
-                    // If the dates aren't the same, then it can't be the same transaction (probably)
-                    if a.date != b.date {
-                        continue;
-                    }
 
-                    // Check if the source of Txn A is listed in Txn B (but not the first)
-                    let a_source = if let Some(a_source) = a.postings.first() {
-                        if !b
-                            .postings
-                            .iter()
-                            .skip(1)
-                            .any(|p| p.account == a_source.account)
-                        {
-                            continue;
-                        }
+    let transactions: Vec<_> = directives
+        .iter()
+        .filter_map(|directive| match &directive.inner {
+            Directive::Transaction(txn) => Some((directive, txn, day_number(&txn.date)?)),
+            _ => None,
+        })
+        .collect();
 
-                        a_source
-                    } else {
-                        continue;
-                    };
-
-                    // And vice versa
-                    let b_source = if let Some(b_source) = b.postings.first() {
-                        if !a
-                            .postings
-                            .iter()
-                            .skip(1)
-                            .any(|p| p.account == b_source.account)
-                        {
+    let mut by_date: BTreeMap<i64, HashMap<Transfer, Vec<usize>>> = BTreeMap::new();
+    for (position, (_, txn, day)) in transactions.iter().enumerate() {
+        let index = by_date.entry(*day).or_default();
+        for transfer in transfers(txn) {
+            let positions = index.entry(transfer).or_default();
+            // A transaction may record the same transfer more than once.
+            if positions.last() != Some(&position) {
+                positions.push(position);
+            }
+        }
+    }
+
+    // A pair of transactions may record several identical transfers,
+    // so collect the pairs before reporting them.
+    let mut pairs: HashMap<(usize, usize), u8> = HashMap::new();
+    for (&day, index) in &by_date {
+        for (&other_day, other_index) in by_date.range(day..=day + window as i64) {
+            for (transfer, positions) in index {
+                let Some(others) = other_index.get(transfer) else {
+                    continue;
+                };
+
+                for &first in positions {
+                    let (_, a, _) = transactions[first];
+
+                    for &second in others {
+                        // Pairs within the same day are only visited once.
+                        if other_day == day && second <= first {
                             continue;
                         }
 
-                        b_source
-                    } else {
-                        continue;
-                    };
+                        let (_, b, _) = transactions[second];
 
-                    // Should also check if accounts are Assets.
+                        // Identical transactions are reported by [`crate::lints::duplicates`]
+                        if a.date == b.date && a.payee == b.payee && a.postings == b.postings {
+                            continue;
+                        }
 
-                    if a_source.units.currency != b_source.units.currency {
-                        continue;
+                        let confidence = confidence(a, b, (other_day - day) as u64);
+                        let pair = (first.min(second), first.max(second));
+                        pairs
+                            .entry(pair)
+                            .and_modify(|existing| *existing = (*existing).max(confidence))
+                            .or_insert(confidence);
                     }
+                }
+            }
+        }
+    }
 
-                    if let (Some(a_num), Some(b_num)) = (a_source.units.num, b_source.units.num) {
-                        if (a_num + b_num).is_zero() {
-                            double_entries.insert(DoubleEntry::from(&[
-                                Sourced {
-                                    inner: a.clone(),
-                                    location: first.location.clone(),
-                                },
-                                Sourced {
-                                    inner: b.clone(),
-                                    location: second.location.clone(),
-                                },
-                            ]));
-                        }
-                    }
+    // The same transfer may have been entered more than twice, so merge overlapping
+    // pairs into groups, reporting the confidence of the most likely pair.
+    let mut groups: Vec<(Vec<usize>, u8)> = Vec::new();
+    let mut group_of: HashMap<usize, usize> = HashMap::new();
+    let mut pairs: Vec<_> = pairs.into_iter().collect();
+    pairs.sort();
+
+    for ((first, second), confidence) in pairs {
+        match (
+            group_of.get(&first).copied(),
+            group_of.get(&second).copied(),
+        ) {
+            (Some(a), Some(b)) if a != b => {
+                // Both already belong to different groups, so merge b into a.
+                let (members, b_confidence) = std::mem::take(&mut groups[b]);
+                for member in &members {
+                    group_of.insert(*member, a);
                 }
+                groups[a].0.extend(members);
+                groups[a].1 = groups[a].1.max(b_confidence).max(confidence);
+            }
+            (Some(group), Some(_)) => {
+                groups[group].1 = groups[group].1.max(confidence);
+            }
+            (Some(group), None) | (None, Some(group)) => {
+                groups[group].0.extend([first, second]);
+                groups[group].1 = groups[group].1.max(confidence);
+                group_of.insert(first, group);
+                group_of.insert(second, group);
+            }
+            (None, None) => {
+                group_of.insert(first, groups.len());
+                group_of.insert(second, groups.len());
+                groups.push((vec![first, second], confidence));
             }
         }
     }
 
+    let mut double_entries: Vec<_> = groups
+        .into_iter()
+        .filter(|(members, _)| !members.is_empty())
+        .map(|(mut members, confidence)| {
+            members.sort();
+            members.dedup();
+
+            let entries: Vec<_> = members
+                .into_iter()
+                .map(|member| {
+                    let (directive, txn, _) = transactions[member];
+                    Sourced {
+                        inner: txn.clone(),
+                        location: directive.location.clone(),
+                    }
+                })
+                .collect();
+
+            DoubleEntry::from(&entries, confidence)
+        })
+        .collect();
+
+    double_entries.sort_by(|a, b| a.entries[0].location.cmp(&b.entries[0].location));
     double_entries.into_iter().map(Lint::from).collect()
 }
 
@@ -144,10 +303,75 @@ mod tests {
         2000-01-01 * "Unrelated Transaction" ""
             Assets:Bank:Savings  1 DKK
             Assets:Bank:Account
+
+        2000-01-03 * "Example Payee" ""
+            Assets:Bank:Account  -200 DKK
+            Assets:Bank:Savings
+
+        2000-01-04 * "Example Payee" ""
+            Assets:Bank:Savings  200 DKK
+            Assets:Bank:Account
+        "#
+        );
+
+        let double_entries = find_double_entries(&ledger.directives(), 0);
+        assert_eq!(double_entries.len(), 1);
+
+        let double_entries = find_double_entries(&ledger.directives(), 1);
+        assert_eq!(double_entries.len(), 2);
+    }
+
+    #[test]
+    fn test_unordered_double_entry() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 * "Transfer to savings" ""
+            Assets:Bank:Savings  1500 DKK
+            Assets:Bank:Account
+
+        2000-01-02 * "Transfer from checking" ""
+            Assets:Bank:Account  -1500.00 DKK
+            Assets:Bank:Savings  1500.00 DKK
+
+        2000-01-01 * "Grocery Store" ""
+            Expenses:Groceries  1500 DKK
+            Assets:Bank:Account
+
+        2000-01-02 * "Grocery Store" ""
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Groceries
+        "#
+        );
+
+        let double_entries = find_double_entries(&ledger.directives(), 2);
+        assert_eq!(double_entries.len(), 1);
+
+        let double_entry = double_entries.first().unwrap();
+        assert!(double_entry.to_string().contains("(90% confidence)"));
+    }
+
+    #[test]
+    fn test_double_entry_group() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 * "Transfer to savings" ""
+            Assets:Bank:Account  -1500 DKK
+            Assets:Bank:Savings
+
+        2000-01-01 * "Transfer from checking" ""
+            Assets:Bank:Savings  1500 DKK
+            Assets:Bank:Account
+
+        2000-01-02 * "Transfer from checking" ""
+            Assets:Bank:Savings  1500 DKK
+            Assets:Bank:Account
         "#
         );
 
-        let double_entries = find_double_entries(&ledger.directives());
+        let double_entries = find_double_entries(&ledger.directives(), 1);
         assert_eq!(double_entries.len(), 1);
+
+        let double_entry = double_entries.first().unwrap().to_string();
+        assert!(double_entry.contains("across 3 transactions"));
     }
 }
@@ -8,7 +8,7 @@ use log::debug;
 use crate::{
     appendix::{AppendixExtractor, IntoAppendices, TransactionWithAppendix},
     ledger::Sourced,
-    location::ToLocationSpan,
+    location::{Location, ToLocationSpan},
     readable::Payees,
 };
 
@@ -19,6 +19,15 @@ pub struct DuplicateAppendix<'a> {
     entries: Vec<TransactionWithAppendix<'a>>,
 }
 
+impl<'a> DuplicateAppendix<'a> {
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        self.entries
+            .iter()
+            .map(|entry| entry.transaction.location.clone())
+            .collect()
+    }
+}
+
 impl<'a> From<DuplicateAppendix<'a>> for Lint<'a> {
     fn from(duplicate_appendix: DuplicateAppendix<'a>) -> Self {
         Lint::DuplicateAppendix(duplicate_appendix)
@@ -0,0 +1,173 @@
+use std::{collections::HashMap, fmt::Display, path::PathBuf};
+
+use beancount_core::Directive;
+use colored::Colorize;
+use itertools::Itertools;
+use log::debug;
+
+use crate::{
+    appendix::{
+        hashes::DocumentHashes, AppendixExtractor, IntoAppendices, TransactionWithAppendix,
+    },
+    ledger::Sourced,
+    location::{Location, ToLocationSpan},
+};
+
+use super::Lint;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DuplicateDocument<'a> {
+    digest: String,
+    entries: Vec<TransactionWithAppendix<'a>>,
+}
+
+impl<'a> DuplicateDocument<'a> {
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        self.entries
+            .iter()
+            .map(|entry| entry.transaction.location.clone())
+            .collect()
+    }
+}
+
+impl<'a> From<DuplicateDocument<'a>> for Lint<'a> {
+    fn from(duplicate_document: DuplicateDocument<'a>) -> Self {
+        Lint::DuplicateDocument(duplicate_document)
+    }
+}
+
+impl<'a> Display for DuplicateDocument<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ids = self
+            .entries
+            .iter()
+            .map(|entry| entry.appendix.id)
+            .unique()
+            .join(", ");
+
+        writeln!(
+            f,
+            "{} appendix ids {} refer to identical documents (sha256 {}):",
+            "warning:".yellow().bold(),
+            ids.bold(),
+            &self.digest[..12],
+        )?;
+
+        for statement in self
+            .entries
+            .iter()
+            .map(|entry| entry.appendix.statement())
+            .unique()
+        {
+            writeln!(f, "    {}", statement.green())?;
+        }
+        writeln!(f)?;
+
+        for source in self
+            .entries
+            .iter()
+            .map(|entry| entry.transaction.location.clone())
+            .to_span(10)
+        {
+            writeln!(f, "{}", source)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn find_duplicate_documents<'a, Extractor: AppendixExtractor<'a>>(
+    directives: &[Sourced<'a, Directive<'a>>],
+    hashes: &mut DocumentHashes,
+) -> Vec<Lint<'a>> {
+    debug!("checking for duplicate documents");
+    let appendices = directives.iter().cloned().into_appendices::<Extractor>();
+
+    let mut by_digest: HashMap<String, Vec<TransactionWithAppendix<'a>>> = HashMap::new();
+    for entry in appendices {
+        let path = PathBuf::from(entry.appendix.statement());
+
+        // Documents which do not exist are reported by [`crate::lints::document_missing`]
+        if !path.exists() {
+            continue;
+        }
+
+        if let Some(digest) = hashes.try_digest(&path) {
+            by_digest.entry(digest).or_default().push(entry);
+        }
+    }
+
+    let mut duplicates: Vec<_> = by_digest
+        .into_iter()
+        .filter(|(_, entries)| {
+            // Many transactions referring to the same appendix is fine, it's only
+            // a problem if the same document has been filed under multiple ids.
+            entries
+                .iter()
+                .map(|entry| entry.appendix.id)
+                .unique()
+                .count()
+                > 1
+        })
+        .map(|(digest, mut entries)| {
+            entries.sort_by(|a, b| a.appendix.cmp(&b.appendix));
+            DuplicateDocument { digest, entries }
+        })
+        .collect();
+
+    duplicates.sort_by(|a, b| a.entries[0].appendix.cmp(&b.entries[0].appendix));
+    duplicates.into_iter().map(Lint::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        appendix::{hashes::DocumentHashes, statement::FromStatementPath},
+        inline_ledger,
+        lints::duplicate_document::find_duplicate_documents,
+    };
+
+    #[test]
+    fn test_duplicate_documents() {
+        let directory = PathBuf::from("target/test-documents/duplicate-documents");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("2000-01-01.1.receipt.pdf"), "receipt").unwrap();
+        std::fs::write(directory.join("2000-01-02.2.receipt.pdf"), "receipt").unwrap();
+        std::fs::write(directory.join("2000-01-03.3.invoice.pdf"), "invoice").unwrap();
+
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 * "Receipt" ""
+            statement: "target/test-documents/duplicate-documents/2000-01-01.1.receipt.pdf"
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Utilities:Power
+
+        2000-01-02 * "Same receipt, filed again" ""
+            statement: "target/test-documents/duplicate-documents/2000-01-02.2.receipt.pdf"
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Utilities:Power
+
+        2000-01-03 * "Invoice" ""
+            statement: "target/test-documents/duplicate-documents/2000-01-03.3.invoice.pdf"
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Utilities:Power
+
+        2000-01-04 * "Invoice, second payment" ""
+            statement: "target/test-documents/duplicate-documents/2000-01-03.3.invoice.pdf"
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Utilities:Power
+        "#
+        );
+
+        let mut hashes = DocumentHashes::default();
+        let duplicates =
+            find_duplicate_documents::<FromStatementPath>(&ledger.directives(), &mut hashes);
+        assert_eq!(duplicates.len(), 1);
+
+        for duplicate in duplicates {
+            println!("{}", duplicate);
+        }
+    }
+}
@@ -4,24 +4,28 @@ use beancount_core::{Account, Date, Directive, IncompleteAmount, Posting, Transa
 use colored::Colorize;
 use log::debug;
 
-use crate::{ledger::Sourced, location::ToLocationSpan};
+use crate::{
+    edit::TextEdit,
+    ledger::Sourced,
+    location::{Location, ToLocationSpan},
+};
 
 use super::Lint;
 
 #[derive(Debug, PartialEq, Eq, Hash)]
-struct PostingFingerprint<'a> {
+pub(crate) struct PostingFingerprint<'a> {
     pub account: &'a Account<'a>,
     pub units: &'a IncompleteAmount<'a>,
 }
 
 #[derive(Debug, PartialEq, Eq, Hash)]
-struct TransactionFingerprint<'a> {
+pub(crate) struct TransactionFingerprint<'a> {
     pub date: &'a Date<'a>,
     pub payee: &'a Option<Cow<'a, str>>,
     pub postings: Vec<PostingFingerprint<'a>>,
 }
 
-trait Fingerprint<'a> {
+pub(crate) trait Fingerprint<'a> {
     type Fingerprint: std::hash::Hash;
     fn fingerprint(&'a self) -> Self::Fingerprint;
 }
@@ -51,18 +55,47 @@ impl<'a> Fingerprint<'a> for Transaction<'a> {
 
 #[derive(Debug, PartialEq, Hash, Eq)]
 pub struct DuplicateTransaction<'a> {
-    entries: [Sourced<'a, Transaction<'a>>; 2],
+    entries: Vec<Sourced<'a, Transaction<'a>>>,
 }
 
 impl<'a> DuplicateTransaction<'a> {
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        self.entries
+            .iter()
+            .map(|entry| entry.location.clone())
+            .collect()
+    }
+
     pub fn from(entries: &[Sourced<'a, Transaction<'a>>]) -> Self {
-        let mut entries: [Sourced<'a, Transaction<'a>>; 2] =
-            [entries[0].clone(), entries[1].clone()];
+        let mut entries = entries.to_vec();
 
         entries.sort_by(|a, b| a.location.cmp(&b.location));
 
         DuplicateTransaction { entries }
     }
+
+    /// Delete every copy but the first, along with the blank line separating
+    /// each copy from the following directive.
+    pub fn fix(&self) -> Vec<TextEdit<'a>> {
+        self.entries[1..]
+            .iter()
+            .map(|entry| {
+                let location = &entry.location;
+                let followed_by_blank_line = location
+                    .ledger()
+                    .original_contents()
+                    .lines()
+                    .nth(location.end() as usize)
+                    .is_some_and(|line| line.trim().is_empty());
+
+                TextEdit::delete(Location::from(
+                    location.ledger(),
+                    location.start(),
+                    location.end() + followed_by_blank_line as u32,
+                ))
+            })
+            .collect()
+    }
 }
 
 impl<'a> From<DuplicateTransaction<'a>> for Lint<'a> {
@@ -75,7 +108,7 @@ impl<'a> Display for DuplicateTransaction<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(
             f,
-            "{} identical transaction {} found in multiple locations:",
+            "{} identical transaction {} found in {} locations:",
             "warning:".yellow().bold(),
             self.entries[0]
                 .inner
@@ -84,14 +117,14 @@ impl<'a> Display for DuplicateTransaction<'a> {
                 .unwrap_or_default()
                 .bold()
                 .green(),
+            self.entries.len(),
         )?;
 
-        for source in [
-            self.entries[0].location.clone(),
-            self.entries[1].location.clone(),
-        ]
-        .into_iter()
-        .to_span(10)
+        for source in self
+            .entries
+            .iter()
+            .map(|entry| entry.location.clone())
+            .to_span(10)
         {
             writeln!(f, "{}", source)?;
         }
@@ -138,7 +171,7 @@ pub fn find_duplicates<'a>(directives: &[Sourced<'a, Directive<'a>>]) -> Vec<Lin
 #[cfg(test)]
 mod tests {
     use super::find_duplicates;
-    use crate::inline_ledger;
+    use crate::{edit::combine, inline_ledger};
 
     #[test]
     fn test_duplicates() {
@@ -160,5 +193,18 @@ mod tests {
 
         let duplicates = find_duplicates(&ledger.directives());
         assert_eq!(duplicates.len(), 1);
+
+        let duplicate = duplicates.first().unwrap().to_string();
+        assert!(duplicate.contains("found in 3 locations"));
+        println!("{}", duplicate);
+
+        let (edits, skipped) = combine(
+            duplicates
+                .iter()
+                .filter_map(|lint| Some((lint, lint.fix()?))),
+        );
+        assert!(skipped.is_empty());
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].contents.matches("Example Payee").count(), 1);
     }
 }
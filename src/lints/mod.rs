@@ -1,30 +1,261 @@
+mod amount_mismatch;
 mod appendix_missing;
+mod chronological;
 mod document_missing;
 mod double_entry;
 mod duplicate_appendix;
+mod duplicate_document;
 mod duplicates;
+mod near_duplicates;
+mod over_budget;
 mod sequential_appendix;
+mod trailing_whitespace;
 mod unbalanced;
 
 use std::fmt::Display;
 
+use beancount_core::Directive;
+use chrono::{Local, NaiveDate};
+use clap::Args;
+
+use crate::{
+    appendix::{hashes::DocumentHashes, statement::FromStatementPath},
+    edit::TextEdit,
+    ledger::{LedgerFile, Sourced},
+    location::Location,
+};
+
+pub use amount_mismatch::find_amount_mismatches;
 pub use appendix_missing::find_missing_appendices;
+pub use chronological::{find_out_of_order, Chronology};
 pub use document_missing::find_missing_documents;
 pub use double_entry::find_double_entries;
 pub use duplicate_appendix::find_duplicate_appendix_ids;
+pub use duplicate_document::find_duplicate_documents;
 pub use duplicates::find_duplicates;
+pub(crate) use duplicates::Fingerprint;
+pub use near_duplicates::find_near_duplicates;
+pub use over_budget::find_over_budget;
 pub use sequential_appendix::find_nonsequential_appendices;
+pub use trailing_whitespace::find_trailing_whitespace;
 pub use unbalanced::find_unbalanced_entries;
 
+/// Tunables of the individual lints, shared by the commands running them.
+#[derive(Debug, Clone, Args)]
+pub struct LintOptions {
+    /// Number of days apart two transactions may be booked, and still be
+    /// considered a potential double entry.
+    #[arg(long, default_value_t = 0)]
+    pub double_entry_window: u32,
+    /// Report transactions which are similar, but not identical, to another
+    /// transaction booked around the same time.
+    #[arg(long)]
+    pub near_duplicates: bool,
+    /// Number of days apart two similar transactions may be booked, and still
+    /// be considered near-duplicates.
+    #[arg(long, default_value_t = 1)]
+    pub near_duplicate_window: u32,
+    /// Minimum similarity of payees, in percent, for two transactions with
+    /// equal amounts to be considered near-duplicates.
+    #[arg(long, default_value_t = 80)]
+    pub near_duplicate_similarity: u8,
+    /// Verify that the amount of each transaction appears in the text
+    /// of the pdf document referred to by its statement.
+    #[arg(long)]
+    pub verify_amounts: bool,
+    /// Report directives dated out of this order within each file.
+    #[arg(long, value_enum)]
+    pub chronological: Option<Chronology>,
+    /// Warn about accounts which have spent more than their budget for the
+    /// current period.
+    #[arg(long)]
+    pub over_budget: bool,
+    /// Date the current budget period is determined from.
+    #[arg(long, default_value_t = Local::now().date_naive())]
+    pub today: NaiveDate,
+}
+
+/// Run every lint against the ledger.
+pub fn run<'a>(
+    files: &[&'a LedgerFile],
+    directives: &[Sourced<'a, Directive<'a>>],
+    hashes: &mut DocumentHashes,
+    options: &LintOptions,
+) -> Vec<Lint<'a>> {
+    let mut lints = run_cross_file(directives, hashes, options);
+    lints.extend(run_file_local(files, directives, options));
+    lints
+}
+
+/// Kinds of the findings of [`run_file_local`], in the order they are reported.
+pub const FILE_LOCAL_KINDS: [&str; 4] = [
+    "unbalanced-entry",
+    "trailing-whitespace",
+    "missing-appendix",
+    "out-of-order",
+];
+
+/// Lints whose findings within a file depend only on the contents of that file.
+pub fn run_file_local<'a>(
+    files: &[&'a LedgerFile],
+    directives: &[Sourced<'a, Directive<'a>>],
+    options: &LintOptions,
+) -> Vec<Lint<'a>> {
+    [
+        find_unbalanced_entries(directives),
+        find_trailing_whitespace(files.iter().copied()),
+        find_missing_appendices::<FromStatementPath>(directives),
+        options
+            .chronological
+            .map(|chronology| find_out_of_order(directives, chronology))
+            .unwrap_or_default(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Lints comparing transactions across files, or looking at the documents
+/// referred to by the ledger.
+pub fn run_cross_file<'a>(
+    directives: &[Sourced<'a, Directive<'a>>],
+    hashes: &mut DocumentHashes,
+    options: &LintOptions,
+) -> Vec<Lint<'a>> {
+    let double_entries = find_double_entries(directives, options.double_entry_window);
+
+    let near_duplicates = if options.near_duplicates {
+        // Transfers entered from both ends look alike, and are already reported
+        // as double entries.
+        let reported: Vec<_> = double_entries.iter().map(Lint::locations).collect();
+        find_near_duplicates(
+            directives,
+            options.near_duplicate_window,
+            options.near_duplicate_similarity,
+        )
+        .into_iter()
+        .filter(|lint| {
+            let locations = lint.locations();
+            !reported
+                .iter()
+                .any(|group| locations.iter().all(|location| group.contains(location)))
+        })
+        .collect()
+    } else {
+        Vec::new()
+    };
+
+    [
+        double_entries,
+        find_duplicates(directives),
+        near_duplicates,
+        find_nonsequential_appendices::<FromStatementPath>(directives),
+        find_duplicate_appendix_ids::<FromStatementPath>(directives),
+        find_missing_documents::<FromStatementPath>(directives),
+        find_duplicate_documents::<FromStatementPath>(directives, hashes),
+        if options.verify_amounts {
+            find_amount_mismatches::<FromStatementPath>(directives)
+        } else {
+            Vec::new()
+        },
+        if options.over_budget {
+            find_over_budget(directives, options.today)
+        } else {
+            Vec::new()
+        },
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
 #[derive(Debug)]
 pub enum Lint<'a> {
     DoubleEntry(double_entry::DoubleEntry<'a>),
     DuplicateTransaction(duplicates::DuplicateTransaction<'a>),
+    NearDuplicate(near_duplicates::NearDuplicate<'a>),
     UnbalancedEntry(unbalanced::UnbalancedEntry<'a>),
     NonSequentialAppendix(sequential_appendix::NonSequentialAppendix<'a>),
     DuplicateAppendix(duplicate_appendix::DuplicateAppendix<'a>),
+    DuplicateDocument(duplicate_document::DuplicateDocument<'a>),
     MissingAppendix(appendix_missing::MissingAppendix<'a>),
     MissingDocument(document_missing::MissingDocument<'a>),
+    AmountMismatch(amount_mismatch::AmountMismatch<'a>),
+    TrailingWhitespace(trailing_whitespace::TrailingWhitespace<'a>),
+    OutOfOrder(chronological::OutOfOrder<'a>),
+    OverBudget(over_budget::OverBudget<'a>),
+}
+
+impl<'a> Lint<'a> {
+    /// Edits resolving the issue, if it can be fixed mechanically without
+    /// any judgement on the part of the user.
+    pub fn fix(&self) -> Option<Vec<TextEdit<'a>>> {
+        match self {
+            Lint::DuplicateTransaction(inner) => Some(inner.fix()),
+            Lint::TrailingWhitespace(inner) => Some(inner.fix()),
+            _ => None,
+        }
+    }
+
+    /// Short name of the kind of finding, which does not depend on its details.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Lint::DoubleEntry(_) => "double-entry",
+            Lint::DuplicateTransaction(_) => "duplicate-transaction",
+            Lint::NearDuplicate(_) => "near-duplicate",
+            Lint::UnbalancedEntry(_) => "unbalanced-entry",
+            Lint::NonSequentialAppendix(_) => "non-sequential-appendix",
+            Lint::DuplicateAppendix(_) => "duplicate-appendix",
+            Lint::DuplicateDocument(_) => "duplicate-document",
+            Lint::MissingAppendix(_) => "missing-appendix",
+            Lint::MissingDocument(_) => "missing-document",
+            Lint::AmountMismatch(_) => "amount-mismatch",
+            Lint::TrailingWhitespace(_) => "trailing-whitespace",
+            Lint::OutOfOrder(_) => "out-of-order",
+            Lint::OverBudget(_) => "over-budget",
+        }
+    }
+
+    /// First line of the finding, without its severity or styling.
+    pub fn summary(&self) -> String {
+        let rendered = self.to_string();
+        let mut summary = String::new();
+        let mut chars = rendered.lines().next().unwrap_or_default().chars();
+
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                // Skip the escape sequence up to and including its final `m`.
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                summary.push(c);
+            }
+        }
+
+        summary
+            .trim_start_matches("warning:")
+            .trim()
+            .trim_end_matches(':')
+            .to_string()
+    }
+
+    /// Locations the finding refers to.
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        match self {
+            Lint::DoubleEntry(inner) => inner.locations(),
+            Lint::DuplicateTransaction(inner) => inner.locations(),
+            Lint::NearDuplicate(inner) => inner.locations(),
+            Lint::UnbalancedEntry(inner) => inner.locations(),
+            Lint::NonSequentialAppendix(inner) => inner.locations(),
+            Lint::DuplicateAppendix(inner) => inner.locations(),
+            Lint::DuplicateDocument(inner) => inner.locations(),
+            Lint::MissingAppendix(inner) => inner.locations(),
+            Lint::MissingDocument(inner) => inner.locations(),
+            Lint::AmountMismatch(inner) => inner.locations(),
+            Lint::TrailingWhitespace(inner) => inner.locations(),
+            Lint::OutOfOrder(inner) => inner.locations(),
+            Lint::OverBudget(inner) => inner.locations(),
+        }
+    }
 }
 
 impl<'a> Display for Lint<'a> {
@@ -32,11 +263,17 @@ impl<'a> Display for Lint<'a> {
         match self {
             Lint::DoubleEntry(inner) => write!(f, "{}", inner),
             Lint::DuplicateTransaction(inner) => write!(f, "{}", inner),
+            Lint::NearDuplicate(inner) => write!(f, "{}", inner),
             Lint::UnbalancedEntry(inner) => write!(f, "{}", inner),
             Lint::NonSequentialAppendix(inner) => write!(f, "{}", inner),
             Lint::DuplicateAppendix(inner) => write!(f, "{}", inner),
+            Lint::DuplicateDocument(inner) => write!(f, "{}", inner),
             Lint::MissingAppendix(inner) => write!(f, "{}", inner),
             Lint::MissingDocument(inner) => write!(f, "{}", inner),
+            Lint::AmountMismatch(inner) => write!(f, "{}", inner),
+            Lint::TrailingWhitespace(inner) => write!(f, "{}", inner),
+            Lint::OutOfOrder(inner) => write!(f, "{}", inner),
+            Lint::OverBudget(inner) => write!(f, "{}", inner),
         }
     }
 }
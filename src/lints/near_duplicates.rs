@@ -0,0 +1,234 @@
+use std::{collections::HashMap, fmt::Display};
+
+use beancount_core::{Directive, Transaction};
+use colored::Colorize;
+use log::debug;
+use rust_decimal::Decimal;
+
+use crate::{
+    amount::resolved_postings,
+    date::day_number,
+    ledger::Sourced,
+    location::{Location, ToLocationSpan},
+    readable::{account_name, Payees},
+};
+
+use super::{double_entry::is_own_account, duplicates::Fingerprint, Lint};
+
+#[derive(Debug, PartialEq, Hash, Eq)]
+pub struct NearDuplicate<'a> {
+    entries: [Sourced<'a, Transaction<'a>>; 2],
+    /// Similarity of the payees, in percent.
+    similarity: u8,
+    days_apart: u64,
+}
+
+impl<'a> NearDuplicate<'a> {
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        self.entries
+            .iter()
+            .map(|entry| entry.location.clone())
+            .collect()
+    }
+}
+
+impl<'a> From<NearDuplicate<'a>> for Lint<'a> {
+    fn from(near_duplicate: NearDuplicate<'a>) -> Self {
+        Lint::NearDuplicate(near_duplicate)
+    }
+}
+
+impl<'a> Display for NearDuplicate<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} similar transactions {} with equal amounts, {} day(s) apart ({}% similarity):",
+            "warning:".yellow().bold(),
+            Payees::from(&self.entries),
+            self.days_apart,
+            self.similarity,
+        )?;
+
+        for source in self
+            .entries
+            .iter()
+            .map(|entry| entry.location.clone())
+            .to_span(10)
+        {
+            writeln!(f, "{}", source)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Lowercase words of the description, leaving out punctuation and anything
+/// containing digits, such as reference numbers, so `ACME  Corp. #1042` and
+/// `acme corp` are considered identical.
+fn normalize(transaction: &Transaction) -> String {
+    transaction
+        .payee
+        .as_deref()
+        .unwrap_or(&transaction.narration)
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !word.chars().any(|c| c.is_ascii_digit()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Find transactions which are not exact duplicates, but are booked within `window`
+/// days of each other, move the same amounts to and from the same own accounts,
+/// and whose payees are at least `min_similarity` percent similar by edit distance.
+pub fn find_near_duplicates<'a>(
+    directives: &[Sourced<'a, Directive<'a>>],
+    window: u32,
+    min_similarity: u8,
+) -> Vec<Lint<'a>> {
+    debug!("checking for near-duplicate transactions");
+
+    let transactions: Vec<_> = directives
+        .iter()
+        .filter_map(|directive| match &directive.inner {
+            Directive::Transaction(txn) => Some((directive, txn, day_number(&txn.date)?)),
+            _ => None,
+        })
+        .collect();
+
+    // Group transactions by the amounts they move to and from our own accounts,
+    // regardless of the categories on the other side, since a hand-entered
+    // transaction might well be categorized differently than an imported one.
+    let mut by_amounts: HashMap<Vec<(String, Option<&str>, Decimal)>, Vec<usize>> = HashMap::new();
+    for (position, (_, txn, _)) in transactions.iter().enumerate() {
+        let mut amounts: Vec<_> = resolved_postings(txn)
+            .into_iter()
+            .filter(|posting| is_own_account(posting.account))
+            .map(|posting| {
+                (
+                    account_name(posting.account),
+                    posting.currency,
+                    posting.amount.normalize(),
+                )
+            })
+            .collect();
+
+        if amounts.is_empty() {
+            continue;
+        }
+
+        amounts.sort();
+        by_amounts.entry(amounts).or_default().push(position);
+    }
+
+    let mut near_duplicates = Vec::new();
+    for mut candidates in by_amounts.into_values() {
+        candidates.sort_by_key(|&position| transactions[position].2);
+
+        for (i, &first) in candidates.iter().enumerate() {
+            let (a_source, a, a_day) = transactions[first];
+
+            for &second in &candidates[i + 1..] {
+                let (b_source, b, b_day) = transactions[second];
+
+                let days_apart = (b_day - a_day).unsigned_abs();
+                if days_apart > window as u64 {
+                    break;
+                }
+
+                // Identical transactions are reported by [`crate::lints::duplicates`]
+                if a.fingerprint() == b.fingerprint() {
+                    continue;
+                }
+
+                let similarity = (strsim::normalized_levenshtein(&normalize(a), &normalize(b))
+                    * 100.0)
+                    .round() as u8;
+                if similarity < min_similarity {
+                    continue;
+                }
+
+                let mut entries = [
+                    Sourced {
+                        inner: a.clone(),
+                        location: a_source.location.clone(),
+                    },
+                    Sourced {
+                        inner: b.clone(),
+                        location: b_source.location.clone(),
+                    },
+                ];
+                entries.sort_by(|a, b| a.location.cmp(&b.location));
+
+                near_duplicates.push(NearDuplicate {
+                    entries,
+                    similarity,
+                    days_apart,
+                });
+            }
+        }
+    }
+
+    near_duplicates.sort_by(|a, b| a.entries[0].location.cmp(&b.entries[0].location));
+    near_duplicates.into_iter().map(Lint::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_near_duplicates;
+    use crate::{date::naive_date, inline_ledger, lints::Lint};
+
+    #[test]
+    fn test_near_duplicates() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 * "ACME  Corp." ""
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Uncategorized
+
+        2000-01-02 * "Acme Corp #1042" ""
+            Expenses:Office  1500 DKK
+            Assets:Bank:Account
+
+        2000-01-02 * "Acme Corp" ""
+            Assets:Bank:Credit  -1500 DKK
+            Expenses:Office
+
+        2000-01-02 * "Grocery Store" ""
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Groceries
+
+        2000-01-09 * "Acme Corp" ""
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Office
+
+        2000-01-09 * "Acme Corp" ""
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Office
+        "#
+        );
+
+        let near_duplicates = find_near_duplicates(&ledger.directives(), 1, 80);
+        assert_eq!(near_duplicates.len(), 1);
+
+        let Lint::NearDuplicate(near_duplicate) = &near_duplicates[0] else {
+            panic!("expected a near-duplicate, found {:?}", near_duplicates[0]);
+        };
+        let paired: Vec<_> = near_duplicate
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    naive_date(&entry.date).unwrap().to_string(),
+                    entry.payee.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            paired,
+            [
+                ("2000-01-01".to_string(), Some("ACME  Corp.")),
+                ("2000-01-02".to_string(), Some("Acme Corp #1042")),
+            ]
+        );
+    }
+}
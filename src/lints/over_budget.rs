@@ -0,0 +1,126 @@
+use std::{fmt::Display, ops::Range};
+
+use beancount_core::Directive;
+use chrono::{Duration, NaiveDate};
+use colored::Colorize;
+use log::debug;
+use rust_decimal::Decimal;
+
+use crate::{
+    budget::{budgeted, budgets, by_account, spent, Budget},
+    ledger::Sourced,
+    location::Location,
+};
+
+use super::Lint;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct OverBudget<'a> {
+    budget: Budget<'a>,
+    period: Range<NaiveDate>,
+    budgeted: Decimal,
+    actual: Decimal,
+}
+
+impl<'a> OverBudget<'a> {
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        vec![self.budget.location.clone()]
+    }
+}
+
+impl<'a> From<OverBudget<'a>> for Lint<'a> {
+    fn from(over_budget: OverBudget<'a>) -> Self {
+        Lint::OverBudget(over_budget)
+    }
+}
+
+impl<'a> Display for OverBudget<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} {} has spent {} of its budget of {} {} from {} to {}:",
+            "warning:".yellow().bold(),
+            self.budget.account.bold(),
+            self.actual.to_string().red().bold(),
+            self.budgeted,
+            self.budget.currency.as_deref().unwrap_or_default(),
+            self.period.start,
+            self.period.end - Duration::days(1),
+        )?;
+
+        writeln!(f, "{}", self.budget.location)
+    }
+}
+
+/// Find accounts which have spent more than their budget for the period
+/// containing `today`, up to and including `today`.
+pub fn find_over_budget<'a>(
+    directives: &[Sourced<'a, Directive<'a>>],
+    today: NaiveDate,
+) -> Vec<Lint<'a>> {
+    debug!("checking for accounts exceeding their budget");
+    let budgets = budgets(directives);
+
+    by_account(&budgets)
+        .into_iter()
+        .filter_map(|((account, currency), budgets)| {
+            let current = budgets.iter().rev().find(|budget| budget.date <= today)?;
+            let period = current.interval.range(today);
+
+            let budgeted = budgeted(&budgets, period.clone());
+            let actual = spent(
+                directives,
+                account,
+                currency,
+                period.start..today + Duration::days(1),
+            );
+
+            (actual > budgeted).then(|| {
+                OverBudget {
+                    budget: (*current).clone(),
+                    period,
+                    budgeted,
+                    actual,
+                }
+                .into()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::find_over_budget;
+    use crate::inline_ledger;
+
+    #[test]
+    fn test_over_budget() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 custom "budget" Expenses:Food "monthly" 3000 DKK
+
+        2000-01-01 custom "budget" Expenses:Travel "monthly" 3000 DKK
+
+        2000-02-02 * "Netto" ""
+            Assets:Bank:Account  -3500 DKK
+            Expenses:Food
+
+        2000-02-03 * "DSB" ""
+            Assets:Bank:Account  -400 DKK
+            Expenses:Travel
+        "#
+        );
+
+        let directives = ledger.directives();
+
+        let february = NaiveDate::from_ymd_opt(2000, 2, 10).unwrap();
+        let lints = find_over_budget(&directives, february);
+        assert_eq!(lints.len(), 1);
+        assert!(lints[0].to_string().contains("Expenses:Food"));
+
+        let january = NaiveDate::from_ymd_opt(2000, 1, 31).unwrap();
+        assert!(find_over_budget(&directives, january).is_empty());
+    }
+}
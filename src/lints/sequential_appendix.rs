@@ -7,7 +7,7 @@ use log::debug;
 use crate::{
     appendix::{AppendixExtractor, TransactionWithAppendix},
     ledger::{Downcast, Sourced},
-    location::ToLocationSpan,
+    location::{Location, ToLocationSpan},
     readable::Payees,
 };
 
@@ -19,6 +19,12 @@ pub struct NonSequentialAppendix<'a> {
     after: TransactionWithAppendix<'a>,
 }
 
+impl<'a> NonSequentialAppendix<'a> {
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        vec![self.after.transaction.location.clone()]
+    }
+}
+
 impl<'a> From<NonSequentialAppendix<'a>> for Lint<'a> {
     fn from(nonsequential_appdendix: NonSequentialAppendix<'a>) -> Self {
         Lint::NonSequentialAppendix(nonsequential_appdendix)
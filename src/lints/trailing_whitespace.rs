@@ -0,0 +1,154 @@
+use std::fmt::Display;
+
+use colored::Colorize;
+use log::debug;
+
+use crate::{
+    edit::TextEdit,
+    ledger::LedgerFile,
+    location::{Location, ToLocationSpan},
+};
+
+use super::Lint;
+
+#[derive(Debug, PartialEq, Hash, Eq)]
+pub struct TrailingWhitespace<'a> {
+    lines: Vec<Location<'a>>,
+}
+
+impl<'a> TrailingWhitespace<'a> {
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        self.lines.clone()
+    }
+
+    /// Replace each offending line with its trimmed counterpart, keeping the line ending.
+    pub fn fix(&self) -> Vec<TextEdit<'a>> {
+        self.lines
+            .iter()
+            .filter_map(|location| {
+                let line = location
+                    .ledger()
+                    .original_contents()
+                    .split_inclusive('\n')
+                    .nth(location.start() as usize)?;
+                let content = line.trim_end_matches(['\n', '\r']);
+
+                Some(TextEdit {
+                    location: location.clone(),
+                    replacement: format!("{}{}", content.trim_end(), &line[content.len()..]),
+                })
+            })
+            .collect()
+    }
+}
+
+impl<'a> From<TrailingWhitespace<'a>> for Lint<'a> {
+    fn from(trailing_whitespace: TrailingWhitespace<'a>) -> Self {
+        Lint::TrailingWhitespace(trailing_whitespace)
+    }
+}
+
+impl<'a> Display for TrailingWhitespace<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} trailing whitespace on {} line(s):",
+            "warning:".yellow().bold(),
+            self.lines.len(),
+        )?;
+
+        for source in self.lines.iter().cloned().to_span(0) {
+            writeln!(f, "{}", source)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Find lines ending in whitespace, reporting one lint per file.
+pub fn find_trailing_whitespace<'a>(
+    files: impl IntoIterator<Item = &'a LedgerFile>,
+) -> Vec<Lint<'a>> {
+    debug!("checking for trailing whitespace");
+
+    files
+        .into_iter()
+        .filter_map(|file| {
+            let lines: Vec<_> = file
+                .original_contents()
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| line.ends_with(char::is_whitespace))
+                .map(|(line_number, _)| {
+                    Location::from(file, line_number as u32, line_number as u32 + 1)
+                })
+                .collect();
+
+            if lines.is_empty() {
+                None
+            } else {
+                Some(TrailingWhitespace { lines })
+            }
+        })
+        .map(Lint::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_trailing_whitespace;
+    use crate::{
+        edit::combine,
+        inline_ledger,
+        lints::{find_duplicates, Lint},
+    };
+
+    #[test]
+    fn test_trailing_whitespace() {
+        let ledger = inline_ledger!(
+            "
+        2000-01-01 * \"Example Payee\" \"\"  
+            Assets:Bank:Account  -1500 DKK\t
+            Assets:Bank:Savings
+        "
+        );
+
+        let lints = find_trailing_whitespace(ledger.files());
+        assert_eq!(lints.len(), 1);
+
+        let (edits, skipped) = combine(lints.iter().filter_map(|lint| Some((lint, lint.fix()?))));
+        assert!(skipped.is_empty());
+        assert_eq!(edits.len(), 1);
+        assert!(edits[0]
+            .contents
+            .lines()
+            .all(|line| line == line.trim_end()));
+    }
+
+    #[test]
+    fn test_overlapping_fixes() {
+        let ledger = inline_ledger!(
+            "
+        2000-01-01 * \"Example Payee\" \"\"
+            Assets:Bank:Account  -1500 DKK
+            Assets:Bank:Savings
+
+        2000-01-01 * \"Example Payee\" \"\"
+            Assets:Bank:Account  -1500 DKK\t
+            Assets:Bank:Savings
+        "
+        );
+
+        let mut lints = find_duplicates(&ledger.directives());
+        lints.extend(find_trailing_whitespace(ledger.files()));
+        assert_eq!(lints.len(), 2);
+
+        // Removing the duplicate also removes the whitespace, so the latter fix is
+        // skipped rather than applied on top, and its finding is handed back.
+        let (edits, skipped) = combine(lints.iter().filter_map(|lint| Some((lint, lint.fix()?))));
+        assert_eq!(edits.len(), 1);
+        assert_eq!(skipped.len(), 1);
+        assert!(matches!(skipped[0], Lint::TrailingWhitespace(_)));
+        assert_eq!(edits[0].contents.matches("Example Payee").count(), 1);
+    }
+}
@@ -4,7 +4,7 @@ use beancount_core::{Directive, Transaction};
 use colored::Colorize;
 use log::debug;
 
-use crate::{ledger::Sourced, readable::Payees};
+use crate::{ledger::Sourced, location::Location, readable::Payees};
 
 use super::Lint;
 
@@ -14,6 +14,10 @@ pub struct UnbalancedEntry<'a> {
 }
 
 impl<'a> UnbalancedEntry<'a> {
+    pub fn locations(&self) -> Vec<Location<'a>> {
+        vec![self.entry.location.clone()]
+    }
+
     pub fn from(entry: Sourced<'a, Transaction<'a>>) -> Self {
         UnbalancedEntry { entry }
     }
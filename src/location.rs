@@ -58,7 +58,7 @@ impl<'a> Location<'a> {
         LocationSpan::from([self.clone()].into_iter(), lines_context).unwrap()
     }
 
-    pub fn ledger(&self) -> &LedgerFile {
+    pub fn ledger(&self) -> &'a LedgerFile {
         self.ledger
     }
 
@@ -69,6 +69,15 @@ impl<'a> Location<'a> {
     pub fn end(&self) -> u32 {
         self.end_line // + self.source.line_offset()
     }
+
+    /// Lines covered by the location, without their line endings.
+    pub fn lines(&self) -> impl Iterator<Item = &'a str> {
+        self.ledger
+            .text_contents
+            .lines()
+            .skip(self.start_line as usize)
+            .take(self.end_line.saturating_sub(self.start_line) as usize)
+    }
 }
 
 impl<'a> Display for Location<'a> {
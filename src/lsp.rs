@@ -0,0 +1,671 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+use beancount_core::Directive;
+use log::{debug, warn};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{Completion, GotoDefinition, HoverRequest, Request as _},
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, MarkupContent, MarkupKind, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentPositionParams,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    amount::resolved_postings,
+    appendix::hashes::{DocumentHashes, HASHES_FILE},
+    date::{directive_date, naive_date},
+    error::Error,
+    ledger::{Ledger, LedgerFile, Sourced},
+    lints::{self, LintOptions},
+    location::Location,
+    readable::account_name,
+};
+
+fn protocol_error(err: impl std::fmt::Display) -> Error {
+    Error::Lsp(err.to_string())
+}
+
+fn range(location: &Location) -> Range {
+    Range {
+        start: Position::new(location.start(), 0),
+        end: Position::new(location.end(), 0),
+    }
+}
+
+/// Number of characters of the line before `character`, which LSP counts in
+/// UTF-16 code units.
+fn char_offset(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    line.chars()
+        .take_while(|c| {
+            units += c.len_utf16();
+            units <= character as usize
+        })
+        .count()
+}
+
+/// The account name, or other word, under the cursor.
+fn word_at(text: &str, position: Position) -> Option<String> {
+    let line = text.lines().nth(position.line as usize)?;
+    let cursor = char_offset(line, position.character);
+
+    let line: Vec<char> = line.chars().collect();
+    let is_word = |c: &char| c.is_alphanumeric() || ":-_".contains(*c);
+
+    let start = line[..cursor]
+        .iter()
+        .rposition(|c| !is_word(c))
+        .map_or(0, |i| i + 1);
+    let end = line[cursor..]
+        .iter()
+        .position(|c| !is_word(c))
+        .map_or(line.len(), |i| cursor + i);
+
+    (start < end).then(|| line[start..end].iter().collect())
+}
+
+/// Directives of every file which currently parses.
+fn parse(ledger: &Ledger) -> Vec<Sourced<'_, Directive<'_>>> {
+    ledger
+        .parsed_files()
+        .filter_map(|(_, parsed)| parsed.ok())
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+pub struct Server {
+    root: PathBuf,
+    options: LintOptions,
+    hashes: DocumentHashes,
+    /// The ledger as currently seen by the editor, including unsaved changes.
+    ledger: Ledger,
+    /// Contents of the documents open in the editor, which may not have been saved yet.
+    documents: HashMap<PathBuf, String>,
+}
+
+impl Server {
+    pub fn new(root: &Path, options: LintOptions) -> Result<Self, Error> {
+        let root = root.canonicalize()?;
+        let ledger = Ledger::from_path(&root).unwrap_or_else(|err| {
+            warn!("failed to load ledger: {}", err);
+            Ledger::from_files(Vec::new())
+        });
+
+        Ok(Server {
+            hashes: DocumentHashes::load(root.join(HASHES_FILE)),
+            root,
+            options,
+            ledger,
+            documents: HashMap::new(),
+        })
+    }
+
+    fn is_ledger_file(&self, path: &Path) -> bool {
+        path.starts_with(&self.root) && path.extension().is_some_and(|ext| ext == "beancount")
+    }
+
+    fn text(&self, path: &Path) -> Option<String> {
+        match self.documents.get(path) {
+            Some(text) => Some(text.clone()),
+            None => std::fs::read_to_string(path).ok(),
+        }
+    }
+
+    /// Diagnostics for every file in the ledger, including empty ones for files
+    /// without findings, so stale diagnostics are cleared.
+    pub fn diagnostics(&mut self) -> Vec<PublishDiagnosticsParams> {
+        let mut diagnostics: BTreeMap<PathBuf, Vec<Diagnostic>> = self
+            .ledger
+            .files()
+            .map(|file| (file.filename(), Vec::new()))
+            .collect();
+
+        let mut directives = Vec::new();
+        for (file, parsed) in self.ledger.parsed_files() {
+            match parsed {
+                Ok(parsed) => directives.extend(parsed.iter().cloned()),
+                Err(err) => diagnostics
+                    .entry(file.filename())
+                    .or_default()
+                    .push(Diagnostic {
+                        severity: Some(DiagnosticSeverity::ERROR),
+                        source: Some("autobean".to_string()),
+                        message: err.to_string(),
+                        ..Default::default()
+                    }),
+            }
+        }
+
+        let files: Vec<_> = self.ledger.files().collect();
+        for lint in lints::run(&files, &directives, &mut self.hashes, &self.options) {
+            let message = lint.summary();
+
+            for location in lint.locations() {
+                diagnostics
+                    .entry(location.ledger().filename())
+                    .or_default()
+                    .push(Diagnostic {
+                        range: range(&location),
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        source: Some("autobean".to_string()),
+                        message: message.clone(),
+                        ..Default::default()
+                    });
+            }
+        }
+
+        if let Err(err) = self.hashes.save(self.root.join(HASHES_FILE)) {
+            warn!("failed to save document hash cache: {}", err);
+        }
+
+        diagnostics
+            .into_iter()
+            .filter_map(|(path, diagnostics)| {
+                Some(PublishDiagnosticsParams {
+                    uri: Url::from_file_path(path).ok()?,
+                    diagnostics,
+                    version: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Location of the `open` directive of the account under the cursor.
+    pub fn definition(&self, params: TextDocumentPositionParams) -> Option<lsp_types::Location> {
+        let path = params.text_document.uri.to_file_path().ok()?;
+        let account = word_at(&self.text(&path)?, params.position)?;
+
+        let directives = parse(&self.ledger);
+
+        directives
+            .iter()
+            .find_map(|directive| match &directive.inner {
+                Directive::Open(open) if account_name(&open.account) == account => {
+                    Some(lsp_types::Location {
+                        uri: Url::from_file_path(directive.location.ledger().filename()).ok()?,
+                        range: range(&directive.location),
+                    })
+                }
+                _ => None,
+            })
+    }
+
+    /// Payees when the cursor is within a string, and account names otherwise.
+    pub fn completion(&self, params: TextDocumentPositionParams) -> Vec<CompletionItem> {
+        let Some(text) = params
+            .text_document
+            .uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| self.text(&path))
+        else {
+            return Vec::new();
+        };
+
+        let before_cursor: String = text
+            .lines()
+            .nth(params.position.line as usize)
+            .map(|line| {
+                line.chars()
+                    .take(char_offset(line, params.position.character))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let in_string = before_cursor.matches('"').count() % 2 == 1;
+
+        let directives = parse(&self.ledger);
+
+        let mut labels = BTreeSet::new();
+        for directive in &directives {
+            match &directive.inner {
+                Directive::Transaction(txn) if in_string => {
+                    labels.extend(txn.payee.as_deref().map(str::to_string));
+                }
+                Directive::Transaction(txn) => labels.extend(
+                    txn.postings
+                        .iter()
+                        .map(|posting| account_name(&posting.account)),
+                ),
+                Directive::Open(open) if !in_string => {
+                    labels.insert(account_name(&open.account));
+                }
+                _ => {}
+            }
+        }
+
+        let kind = if in_string {
+            CompletionItemKind::TEXT
+        } else {
+            CompletionItemKind::MODULE
+        };
+
+        labels
+            .into_iter()
+            .map(|label| CompletionItem {
+                label,
+                kind: Some(kind),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Balance of the account under the cursor, as of the date of the directive
+    /// the cursor is in, or of all transactions outside of any directive.
+    pub fn hover(&self, params: TextDocumentPositionParams) -> Option<Hover> {
+        let path = params.text_document.uri.to_file_path().ok()?;
+        let account = word_at(&self.text(&path)?, params.position)?;
+        if !account.contains(':') {
+            return None;
+        }
+
+        let directives = parse(&self.ledger);
+
+        let date = directives
+            .iter()
+            .filter(|directive| {
+                directive.location.ledger().filename() == path
+                    && (directive.location.start()..directive.location.end())
+                        .contains(&params.position.line)
+            })
+            .find_map(|directive| naive_date(directive_date(&directive.inner)?));
+
+        let mut balances: BTreeMap<Option<&str>, Decimal> = BTreeMap::new();
+        for directive in &directives {
+            let Directive::Transaction(txn) = &directive.inner else {
+                continue;
+            };
+
+            let Some(txn_date) = naive_date(&txn.date) else {
+                continue;
+            };
+
+            if date.is_some_and(|date| txn_date > date) {
+                continue;
+            }
+
+            for posting in resolved_postings(txn) {
+                if account_name(posting.account) == account {
+                    *balances.entry(posting.currency).or_default() += posting.amount;
+                }
+            }
+        }
+
+        let mut value = match date {
+            Some(date) => format!("**{}** as of {}:\n", account, date),
+            None => format!("**{}**:\n", account),
+        };
+
+        if balances.is_empty() {
+            value.push_str("\n- 0\n");
+        }
+
+        for (currency, amount) in balances {
+            value.push_str(&format!("\n- {} {}", amount, currency.unwrap_or_default()));
+        }
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: None,
+        })
+    }
+
+    /// Keep the contents of an opened or changed document, replacing the
+    /// file in the ledger if it is one, so only that file is parsed again.
+    fn open(&mut self, uri: &Url, text: String) -> bool {
+        let Ok(path) = uri.to_file_path() else {
+            return false;
+        };
+
+        if self.is_ledger_file(&path) {
+            self.ledger.insert(LedgerFile {
+                source: path.clone().into(),
+                // Parsing fails if there are no trailing newlines.
+                text_contents: format!("{}\n", text),
+            });
+        }
+
+        self.documents.insert(path, text);
+        true
+    }
+
+    /// Forget the unsaved changes of a closed document, going back to the file on disk.
+    fn close(&mut self, uri: &Url) {
+        let Ok(path) = uri.to_file_path() else {
+            return;
+        };
+
+        self.documents.remove(&path);
+        if !self.is_ledger_file(&path) {
+            return;
+        }
+
+        match LedgerFile::from_file(path.clone()) {
+            Ok(file) => self.ledger.insert(file),
+            Err(err) => {
+                debug!("closed {}: {}", path.to_string_lossy(), err);
+                self.ledger.remove(&path);
+            }
+        }
+    }
+
+    /// Handle a notification, returning whether diagnostics should be republished.
+    fn handle_notification(&mut self, notification: Notification) -> bool {
+        fn params<P: DeserializeOwned>(notification: Notification) -> Option<P> {
+            serde_json::from_value(notification.params)
+                .map_err(|err| warn!("invalid notification params: {}", err))
+                .ok()
+        }
+
+        let method = notification.method.clone();
+        match method.as_str() {
+            DidOpenTextDocument::METHOD => params::<DidOpenTextDocumentParams>(notification)
+                .is_some_and(|params| {
+                    self.open(&params.text_document.uri, params.text_document.text)
+                }),
+            DidChangeTextDocument::METHOD => params::<DidChangeTextDocumentParams>(notification)
+                .is_some_and(|mut params| match params.content_changes.pop() {
+                    Some(change) => self.open(&params.text_document.uri, change.text),
+                    None => false,
+                }),
+            DidSaveTextDocument::METHOD => true,
+            DidCloseTextDocument::METHOD => params::<DidCloseTextDocumentParams>(notification)
+                .is_some_and(|params| {
+                    self.close(&params.text_document.uri);
+                    true
+                }),
+            method => {
+                debug!("ignoring notification {}", method);
+                false
+            }
+        }
+    }
+
+    fn handle_request(&mut self, request: Request) -> Response {
+        fn respond<P: DeserializeOwned, R: Serialize>(
+            request: Request,
+            handler: impl FnOnce(P) -> R,
+        ) -> Response {
+            match serde_json::from_value(request.params) {
+                Ok(params) => Response::new_ok(request.id, handler(params)),
+                Err(err) => {
+                    Response::new_err(request.id, ErrorCode::InvalidParams as i32, err.to_string())
+                }
+            }
+        }
+
+        let method = request.method.clone();
+        match method.as_str() {
+            GotoDefinition::METHOD => respond(request, |params: GotoDefinitionParams| {
+                self.definition(params.text_document_position_params)
+                    .map(GotoDefinitionResponse::Scalar)
+            }),
+            Completion::METHOD => respond(request, |params: CompletionParams| {
+                CompletionResponse::Array(self.completion(params.text_document_position))
+            }),
+            HoverRequest::METHOD => respond(request, |params: HoverParams| {
+                self.hover(params.text_document_position_params)
+            }),
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request {}", method),
+            ),
+        }
+    }
+
+    fn publish(&mut self, connection: &Connection) -> Result<(), Error> {
+        for params in self.diagnostics() {
+            connection
+                .sender
+                .send(Notification::new(PublishDiagnostics::METHOD.to_string(), params).into())
+                .map_err(protocol_error)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Serve the ledger in `root` over an established connection, until the client shuts down.
+pub fn serve(connection: Connection, root: &Path, options: LintOptions) -> Result<(), Error> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![":".to_string(), "\"".to_string()]),
+            ..Default::default()
+        }),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..Default::default()
+    };
+
+    connection
+        .initialize(serde_json::to_value(capabilities).map_err(protocol_error)?)
+        .map_err(protocol_error)?;
+
+    let mut server = Server::new(root, options)?;
+    server.publish(&connection)?;
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection
+                    .handle_shutdown(&request)
+                    .map_err(protocol_error)?
+                {
+                    return Ok(());
+                }
+
+                let response = server.handle_request(request);
+                connection
+                    .sender
+                    .send(response.into())
+                    .map_err(protocol_error)?;
+            }
+            Message::Notification(notification) => {
+                if server.handle_notification(notification) {
+                    server.publish(&connection)?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Serve the ledger in `root` over stdin and stdout.
+pub fn run(root: &Path, options: LintOptions) -> Result<(), Error> {
+    let (connection, io_threads) = Connection::stdio();
+    serve(connection, root, options)?;
+    io_threads.join()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use lsp_server::{Connection, Message, Notification, Request};
+    use lsp_types::{
+        notification::{Exit, Initialized, Notification as _, PublishDiagnostics},
+        request::{GotoDefinition, HoverRequest, Initialize, Request as _, Shutdown},
+        GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+        InitializeParams, InitializedParams, Position, PublishDiagnosticsParams,
+        TextDocumentIdentifier, TextDocumentPositionParams, Url,
+    };
+
+    use super::{serve, word_at};
+    use crate::lints::LintOptions;
+
+    fn response(client: &Connection, id: i32) -> serde_json::Value {
+        for message in &client.receiver {
+            if let Message::Response(response) = message {
+                if response.id == id.into() {
+                    return response.result.unwrap_or_default();
+                }
+            }
+        }
+
+        panic!("connection closed before response {}", id);
+    }
+
+    #[test]
+    fn test_word_at() {
+        // Positions count UTF-16 code units, of which each emoji takes up two.
+        let text = "🍰🍰🍰🍰 Food x";
+        assert_eq!(
+            word_at(text, Position::new(0, 11)),
+            Some("Food".to_string())
+        );
+        assert_eq!(word_at(text, Position::new(0, 15)), Some("x".to_string()));
+    }
+
+    #[test]
+    fn test_language_server() {
+        let root = std::path::PathBuf::from("target/test-lsp");
+        std::fs::remove_dir_all(&root).ok();
+        std::fs::create_dir_all(&root).unwrap();
+
+        let transaction =
+            "2000-01-02 * \"Office Supplies\" \"\"\n  Assets:Bank:Account  -1500 DKK\n  Expenses:Office\n";
+        std::fs::write(
+            root.join("ledger.beancount"),
+            format!(
+                "2000-01-01 open Assets:Bank:Account\n2000-01-01 open Expenses:Office\n\n{}\n{}",
+                transaction, transaction
+            ),
+        )
+        .unwrap();
+
+        let uri =
+            Url::from_file_path(root.canonicalize().unwrap().join("ledger.beancount")).unwrap();
+        let options = LintOptions {
+            double_entry_window: 0,
+            near_duplicates: false,
+            near_duplicate_window: 1,
+            near_duplicate_similarity: 80,
+            verify_amounts: false,
+            chronological: None,
+            over_budget: false,
+            today: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+        };
+
+        let (server, client) = Connection::memory();
+        let server_root = root.clone();
+        let server = std::thread::spawn(move || serve(server, &server_root, options));
+
+        client
+            .sender
+            .send(
+                Request::new(
+                    1.into(),
+                    Initialize::METHOD.to_string(),
+                    InitializeParams::default(),
+                )
+                .into(),
+            )
+            .unwrap();
+        response(&client, 1);
+        client
+            .sender
+            .send(Notification::new(Initialized::METHOD.to_string(), InitializedParams {}).into())
+            .unwrap();
+
+        // The duplicated transaction is reported as soon as the server has started.
+        let diagnostics = client
+            .receiver
+            .iter()
+            .find_map(|message| match message {
+                Message::Notification(notification)
+                    if notification.method == PublishDiagnostics::METHOD =>
+                {
+                    serde_json::from_value::<PublishDiagnosticsParams>(notification.params)
+                        .ok()
+                        .filter(|params| params.uri == uri)
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert!(diagnostics
+            .diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.message.contains("identical transaction")));
+
+        let position = TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position: Position::new(4, 6),
+        };
+
+        client
+            .sender
+            .send(
+                Request::new(
+                    2.into(),
+                    GotoDefinition::METHOD.to_string(),
+                    GotoDefinitionParams {
+                        text_document_position_params: position.clone(),
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    },
+                )
+                .into(),
+            )
+            .unwrap();
+        let definition: Option<GotoDefinitionResponse> =
+            serde_json::from_value(response(&client, 2)).unwrap();
+        match definition {
+            Some(GotoDefinitionResponse::Scalar(location)) => {
+                assert_eq!(location.range.start.line, 0)
+            }
+            other => panic!("unexpected definition {:?}", other),
+        }
+
+        client
+            .sender
+            .send(
+                Request::new(
+                    3.into(),
+                    HoverRequest::METHOD.to_string(),
+                    HoverParams {
+                        text_document_position_params: position,
+                        work_done_progress_params: Default::default(),
+                    },
+                )
+                .into(),
+            )
+            .unwrap();
+        let hover: Hover = serde_json::from_value(response(&client, 3)).unwrap();
+        match hover.contents {
+            HoverContents::Markup(markup) => assert!(markup.value.contains("-3000 DKK")),
+            other => panic!("unexpected hover {:?}", other),
+        }
+
+        client
+            .sender
+            .send(Request::new(4.into(), Shutdown::METHOD.to_string(), ()).into())
+            .unwrap();
+        response(&client, 4);
+        client
+            .sender
+            .send(Notification::new(Exit::METHOD.to_string(), ()).into())
+            .unwrap();
+
+        server.join().unwrap().unwrap();
+    }
+}
@@ -1,29 +1,48 @@
-mod annual;
-mod appendix;
-mod balance;
-mod error;
-mod ledger;
-mod lints;
-mod location;
-mod readable;
-
-use std::{collections::HashMap, path::PathBuf, process::exit};
-
-use appendix::statement::FromStatementPath;
-use beancount_core::Transaction;
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    ops::RangeInclusive,
+    path::PathBuf,
+    process::exit,
+    time::{Duration, Instant},
+};
+
+use autobean::{
+    annual,
+    appendix::{
+        hashes::{DocumentHashes, HASHES_FILE},
+        intake::{next_appendix_id, Attachment},
+        listing::{appendices_csv, list_appendices, parse_id_range, AppendixFilter},
+        renumber::Renumbering,
+        statement::FromStatementPath,
+    },
+    balance::{self, balance},
+    budget::{self, budgets},
+    cache::{self, CACHE_DIR},
+    categorize::categorize_ledger,
+    edit::combine,
+    format::{format_ledger, FormatOptions},
+    import::{
+        self, classifier::Classifier, csv::CsvOptions, parse_account, rules::Rules, Encoding,
+        ImportFormat,
+    },
+    ledger::Ledger,
+    lints::{self, Chronology, Lint, LintOptions},
+    lsp, query,
+    reconcile::reconcile,
+    register::{self, account_glob, register},
+    sort::sort_ledger,
+    trend::{trend, Period},
+    watch::watch,
+};
+use chrono::{Local, NaiveDate};
 use clap::{Parser, Subcommand, ValueEnum};
 use colored::Colorize;
-use itertools::Itertools;
-use ledger::Ledger;
-use log::{debug, warn, LevelFilter};
+use log::{debug, info, warn, LevelFilter};
+use once_cell::unsync::Lazy;
+use rust_decimal::Decimal;
 use tabled::{settings::Style, Table};
 
-use crate::{
-    appendix::{Appendix, AppendixExtractor},
-    balance::balance,
-    ledger::Downcast,
-};
-
 /// Lints beancount files in a directory
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -53,12 +72,260 @@ enum TableStyle {
     Psql,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
 #[derive(Debug, Subcommand)]
 enum Commands {
     /// Check ledger for all lints.
-    Check,
+    Check {
+        #[command(flatten)]
+        lints: LintOptions,
+        /// Apply fixes for issues which can be resolved mechanically, such as
+        /// exact duplicates and trailing whitespace.
+        #[arg(long)]
+        fix: bool,
+        /// Print the fixes as a diff, without changing anything.
+        #[arg(long, requires = "fix")]
+        dry_run: bool,
+        /// Run every lint from scratch, rather than re-using the findings of
+        /// previous runs for unchanged files.
+        #[arg(long)]
+        no_cache: bool,
+    },
+    /// Watch the ledger directory, re-running the checks whenever files change
+    /// and reporting new and resolved findings.
+    Watch {
+        #[command(flatten)]
+        lints: LintOptions,
+        /// Milliseconds to wait for further changes before re-running the checks.
+        #[arg(long, default_value_t = 500)]
+        debounce: u64,
+    },
+    /// Run a language server over stdin and stdout, publishing the findings
+    /// of the checks as diagnostics.
+    Lsp {
+        #[command(flatten)]
+        lints: LintOptions,
+    },
     /// List all appendices listed in the ledger.
-    ListAppendices,
+    ListAppendices {
+        /// Only consider transactions from this year.
+        #[arg(long, short)]
+        year: Option<usize>,
+        /// Only consider transactions with postings to this account, or its sub-accounts.
+        #[arg(long, short)]
+        account: Option<String>,
+        /// Only list appendices whose documents are missing.
+        #[arg(long)]
+        missing_file: bool,
+        /// Only list appendices within this range of ids, e.g. 10..20
+        #[arg(long, value_parser = parse_id_range)]
+        id_range: Option<RangeInclusive<u64>>,
+        /// Output format
+        #[arg(long, short, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print the next free appendix id.
+    NextAppendixId,
+    /// File a receipt into the documents folder as the next appendix,
+    /// and print a transaction skeleton referring to it.
+    Attach {
+        /// Receipt or invoice to file.
+        receipt: PathBuf,
+        /// Date of the transaction, formatted as YYYY-MM-DD.
+        #[arg(long, short)]
+        date: String,
+        /// Folder in which appendices are stored.
+        #[arg(long, default_value = "documents")]
+        documents: PathBuf,
+        /// Descriptive part of the document name.
+        /// Defaults to the name of the receipt file.
+        #[arg(long)]
+        slug: Option<String>,
+        /// Payee of the transaction skeleton.
+        #[arg(long, short)]
+        payee: Option<String>,
+        /// Account the amount is paid from.
+        #[arg(long, default_value = "Assets:Bank")]
+        account: String,
+        /// Account the amount is paid to.
+        #[arg(long, default_value = "Expenses:Uncategorized")]
+        counter_account: String,
+        /// Amount paid.
+        #[arg(long)]
+        amount: Option<Decimal>,
+        /// Currency of the amount paid.
+        #[arg(long, default_value = "DKK")]
+        currency: String,
+        /// Append the transaction skeleton to this file, instead of printing it.
+        #[arg(long)]
+        append: Option<PathBuf>,
+    },
+    /// Renumber appendices into a gap-free sequence ordered by date, renaming
+    /// the documents and rewriting the statements referring to them.
+    RenumberAppendices {
+        /// Print the planned renames and edits as a diff, without changing anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Convert a bank export into transactions, leaving out those already
+    /// in the ledger.
+    Import {
+        /// Bank export to import.
+        statement: PathBuf,
+        /// Format of the export. Detected from its extension if not given.
+        #[arg(long, value_enum)]
+        format: Option<ImportFormat>,
+        /// Character encoding of the export, UTF-8 if not given.
+        #[arg(long, value_enum)]
+        encoding: Option<Encoding>,
+        /// Account the statement belongs to.
+        #[arg(long, short)]
+        account: String,
+        /// Currency of the account, unless stated by the export.
+        #[arg(long, default_value = "DKK")]
+        currency: String,
+        /// File of categorization rules, each line holding an account followed
+        /// by a pattern matched against the text of the statement lines.
+        #[arg(long)]
+        rules: Option<PathBuf>,
+        /// Account lines neither matched by any rule nor confidently
+        /// categorized from the history of the ledger are booked to.
+        #[arg(long, default_value = "Expenses:Uncategorized")]
+        counter_account: String,
+        /// Confidence, in percent, needed to book a line to the account
+        /// suggested by the history of the ledger.
+        #[arg(long, default_value_t = 80)]
+        confidence: u8,
+        #[command(flatten)]
+        csv: CsvOptions,
+        /// Append the transactions to this file, instead of printing them.
+        #[arg(long)]
+        append: Option<PathBuf>,
+    },
+    /// Match the lines of a bank statement to the postings of an account,
+    /// reporting those missing on either side and the difference in closing balance.
+    Reconcile {
+        /// Account the statement belongs to.
+        #[arg(long, short)]
+        account: String,
+        /// Bank statement to reconcile against.
+        statement: PathBuf,
+        /// Format of the statement. Detected from its extension if not given.
+        #[arg(long, value_enum)]
+        format: Option<ImportFormat>,
+        /// Character encoding of the statement, UTF-8 if not given.
+        #[arg(long, value_enum)]
+        encoding: Option<Encoding>,
+        /// Currency of the account, unless stated by the statement.
+        #[arg(long, default_value = "DKK")]
+        currency: String,
+        /// Number of days apart a statement line and its posting may be dated.
+        #[arg(long, default_value_t = 3)]
+        window: u32,
+        #[command(flatten)]
+        csv: CsvOptions,
+    },
+    /// Book uncategorized postings to the accounts suggested by the history
+    /// of the ledger.
+    Categorize {
+        /// Account uncategorized postings are booked to.
+        #[arg(long, default_value = "Expenses:Uncategorized")]
+        uncategorized: String,
+        /// Confidence, in percent, needed to re-book a posting.
+        #[arg(long, default_value_t = 80)]
+        confidence: u8,
+        /// Print the changes as a diff, without changing anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Format the ledger files, aligning amounts and normalizing whitespace.
+    Fmt {
+        /// Column at which posting amounts end.
+        #[arg(long, default_value_t = 60)]
+        column: usize,
+        /// Number of spaces postings and metadata are indented by.
+        #[arg(long, default_value_t = 2)]
+        indent: usize,
+        /// Print the changes as a diff and fail if any file is not formatted,
+        /// without changing anything.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Reorder the directives of each ledger file by date.
+    Sort {
+        /// Order in which to sort the directives.
+        #[arg(long, value_enum, default_value_t = Chronology::Ascending)]
+        order: Chronology,
+        /// Print the changes as a diff, without changing anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List the postings to the matching accounts in date order, with the
+    /// running balance.
+    Register {
+        /// Accounts to list, e.g. `Expenses:*`. Subaccounts are included.
+        account: String,
+        /// Earliest date to list postings from.
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// Latest date to list postings up to and including.
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// Style of the output table
+        #[arg(long, short, value_enum, default_value_t = TableStyle::Blank)]
+        style: TableStyle,
+    },
+    /// Sum the postings to each account by period, with totals per account
+    /// and per period.
+    Trend {
+        /// Length of the periods to sum over.
+        #[arg(long, short, value_enum, default_value_t = Period::Month)]
+        period: Period,
+        /// Only include this account and its subaccounts, e.g. `Expenses`.
+        #[arg(long, short)]
+        account: Option<String>,
+        /// Sum accounts deeper than this into their parent account.
+        #[arg(long)]
+        depth: Option<usize>,
+        /// Print the matrix as CSV instead of a table.
+        #[arg(long)]
+        csv: bool,
+        /// Style of the output table
+        #[arg(long, short, value_enum, default_value_t = TableStyle::Blank)]
+        style: TableStyle,
+    },
+    /// Compare the budgets given by `custom "budget"` directives with the
+    /// postings to each account, period by period.
+    Budget {
+        /// Length of the periods to compare.
+        #[arg(long, short, value_enum, default_value_t = Period::Month)]
+        period: Period,
+        /// Earliest date to compare from. Defaults to the date of the first budget.
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// Latest date to compare up to. Defaults to today.
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// Style of the output table
+        #[arg(long, short, value_enum, default_value_t = TableStyle::Blank)]
+        style: TableStyle,
+    },
+    /// Run a query over the postings of the ledger, written in a subset of
+    /// the beancount query language.
+    Query {
+        /// Query to run, or the name of a `query` directive in the ledger.
+        query: String,
+        /// Style of the output table
+        #[arg(long, short, value_enum, default_value_t = TableStyle::Blank)]
+        style: TableStyle,
+    },
     /// Produce a complete accounting of the given year.
     AnnualAccounts {
         /// Year whose transactions are to be considered.
@@ -88,33 +355,113 @@ fn main() {
         .filter_level(args.debug)
         .init();
 
-    let ledger = Ledger::from_path(&args.path).unwrap();
     debug!("loading ledgers from: {}", &args.path);
+    let start = Instant::now();
+    let ledger = Ledger::from_path(&args.path).unwrap();
+    info!(
+        "read {} files in {:.2?}",
+        ledger.files().len(),
+        start.elapsed()
+    );
 
-    let directives = ledger.directives();
-    if directives.is_empty() {
-        warn!("ledger contains no directives, are you sure the directory contains any beancount files?");
-    } else {
-        debug!("compiled ledger contains {} directives", directives.len());
-    }
+    // Parsed on first use, since `check` may be able to rely on cached findings instead.
+    let directives = Lazy::new(|| {
+        let directives = ledger.directives();
+        if directives.is_empty() {
+            warn!("ledger contains no directives, are you sure the directory contains any beancount files?");
+        } else {
+            debug!("compiled ledger contains {} directives", directives.len());
+        }
+        directives
+    });
 
     match args.command {
-        Commands::Check => {
-            let lints: Vec<_> = [
-                lints::find_double_entries(&directives),
-                lints::find_duplicates(&directives),
-                lints::find_unbalanced_entries(&directives),
-                lints::find_nonsequential_appendices::<FromStatementPath>(&directives),
-                lints::find_duplicate_appendix_ids::<FromStatementPath>(&directives),
-                lints::find_missing_appendices::<FromStatementPath>(&directives),
-                lints::find_missing_documents::<FromStatementPath>(&directives),
-            ]
-            .into_iter()
-            .flatten()
-            .collect();
+        Commands::Check {
+            lints: options,
+            fix,
+            dry_run,
+            no_cache,
+        } => {
+            let hashes_path = PathBuf::from(&args.path).join(HASHES_FILE);
+            let mut hashes = DocumentHashes::load(&hashes_path);
+
+            // Fixes need the lints themselves, rather than their cached findings.
+            if !no_cache && !fix {
+                let start = Instant::now();
+                let findings = cache::check(
+                    &ledger,
+                    &PathBuf::from(&args.path).join(CACHE_DIR),
+                    &mut hashes,
+                    &options,
+                );
+                info!("ran lints in {:.2?}", start.elapsed());
+
+                if let Err(err) = hashes.save(&hashes_path) {
+                    warn!("failed to save document hash cache: {}", err);
+                }
+
+                for finding in &findings {
+                    eprint!("{}", finding.render(&ledger));
+                }
+
+                exit(if findings.is_empty() { 0 } else { 1 })
+            }
+
+            let start = Instant::now();
+            let files: Vec<_> = ledger.files().collect();
+            let lints = lints::run(&files, &directives, &mut hashes, &options);
+            info!("ran lints in {:.2?}", start.elapsed());
+
+            if let Err(err) = hashes.save(&hashes_path) {
+                warn!("failed to save document hash cache: {}", err);
+            }
 
             debug!("discovered {} issues", lints.len());
 
+            let lints: Vec<_> = if fix {
+                let (fixable, unfixable): (Vec<_>, Vec<_>) =
+                    lints.into_iter().partition(|lint| lint.fix().is_some());
+                let (edits, skipped) = combine(
+                    fixable
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(index, lint)| Some((index, lint.fix()?))),
+                );
+
+                if dry_run {
+                    for edit in &edits {
+                        print!("{}", edit.diff());
+                    }
+                    fixable.into_iter().chain(unfixable).collect()
+                } else {
+                    for edit in &edits {
+                        edit.apply().unwrap();
+                    }
+                    eprintln!(
+                        "fixed {} issues in {} files",
+                        fixable.len() - skipped.len(),
+                        edits.len()
+                    );
+                    if !skipped.is_empty() {
+                        warn!(
+                            "{} fixes overlapped other fixes and were not applied, run `check --fix` again",
+                            skipped.len()
+                        );
+                    }
+
+                    // Findings whose fix was skipped remain, and are reported along with the rest.
+                    fixable
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(index, _)| skipped.contains(index))
+                        .map(|(_, lint)| lint)
+                        .chain(unfixable)
+                        .collect()
+                }
+            } else {
+                lints
+            };
+
             for lint in &lints {
                 eprint!("{}", lint);
             }
@@ -125,52 +472,326 @@ fn main() {
 
             exit(0)
         }
-        Commands::ListAppendices => {
-            // Use a HashSet to uniquely identify each appendix
-            let appendices: HashMap<Appendix, Vec<_>> = directives
-                .iter()
-                .cloned()
-                .filter_map(Transaction::downcast)
-                .filter_map(|transaction| {
-                    if let Ok(appendix) = FromStatementPath::from_transaction(transaction.clone()) {
-                        Some((appendix, transaction))
-                    } else {
-                        None
+        Commands::Watch {
+            lints: options,
+            debounce,
+        } => {
+            watch(
+                &PathBuf::from(&args.path),
+                options,
+                Duration::from_millis(debounce),
+            )
+            .unwrap();
+        }
+        Commands::Lsp { lints: options } => {
+            lsp::run(&PathBuf::from(&args.path), options).unwrap();
+        }
+        Commands::ListAppendices {
+            year,
+            account,
+            missing_file,
+            id_range,
+            format,
+        } => {
+            let filter = AppendixFilter {
+                year,
+                account,
+                missing_file,
+                ids: id_range,
+            };
+
+            let appendices = list_appendices::<FromStatementPath>(&directives, &filter);
+
+            match format {
+                OutputFormat::Text => {
+                    for appendix in appendices {
+                        println!("{}", appendix);
                     }
-                })
-                .into_group_map();
-
-            // Convert to Vec, since HashSets by design are unordered.
-            let mut appendices: Vec<_> = appendices.into_iter().collect();
-            appendices.sort_by(|(a, _), (b, _)| a.cmp(b));
-
-            for (Appendix { id, statement }, transactions) in appendices {
-                println!("{id: >8} {statement}", statement = statement.bold().green());
-                for transaction in transactions {
-                    println!(
-                        "       ↳ {ledger}:{line}",
-                        ledger = transaction
-                            .location
-                            .ledger()
-                            .source
-                            .filename()
-                            .to_string_lossy(),
-                        line = transaction.location.start()
+                }
+                OutputFormat::Json => {
+                    println!("{}", serde_json::to_string_pretty(&appendices).unwrap())
+                }
+                OutputFormat::Csv => print!("{}", appendices_csv(&appendices).unwrap()),
+            }
+        }
+        Commands::NextAppendixId => {
+            println!("{}", next_appendix_id::<FromStatementPath>(&directives));
+        }
+        Commands::Attach {
+            receipt,
+            date,
+            documents,
+            slug,
+            payee,
+            account,
+            counter_account,
+            amount,
+            currency,
+            append,
+        } => {
+            // Open the target before the receipt is moved, so a bad path
+            // does not leave the receipt filed without its transaction.
+            let target = match append
+                .as_ref()
+                .map(|append| OpenOptions::new().append(true).open(append))
+                .transpose()
+            {
+                Ok(target) => target,
+                Err(err) => {
+                    eprintln!(
+                        "failed to open {}: {}",
+                        append.unwrap().to_string_lossy(),
+                        err
                     );
+                    exit(1)
+                }
+            };
+
+            let id = next_appendix_id::<FromStatementPath>(&directives);
+            let attachment = match Attachment::new(&receipt, &documents, &date, id, slug.as_deref())
+                .and_then(|attachment| attachment.file(&receipt).map(|_| attachment))
+            {
+                Ok(attachment) => attachment,
+                Err(err) => {
+                    eprintln!("failed to file {}: {}", receipt.to_string_lossy(), err);
+                    exit(1)
+                }
+            };
+
+            let skeleton = attachment.skeleton(
+                payee.as_deref(),
+                &account,
+                &counter_account,
+                amount.map(|amount| (amount, currency.as_str())),
+            );
+
+            if let (Some(mut file), Some(append)) = (target, append) {
+                write!(file, "\n{}", skeleton).unwrap();
+                eprintln!(
+                    "filed {} and appended transaction to {}",
+                    attachment.statement.to_string_lossy().bold().green(),
+                    append.to_string_lossy()
+                );
+            } else {
+                eprintln!(
+                    "filed {}",
+                    attachment.statement.to_string_lossy().bold().green()
+                );
+                print!("{}", skeleton);
+            }
+        }
+        Commands::Import {
+            statement,
+            format,
+            encoding,
+            account,
+            currency,
+            rules,
+            counter_account,
+            confidence,
+            csv,
+            append,
+        } => {
+            let account = parse_account(&account).unwrap();
+            let counter_account = parse_account(&counter_account).unwrap();
+            let classifier = Classifier::learn(&directives, &counter_account);
+            let rules = match rules {
+                Some(rules) => Rules::load(&rules, counter_account).unwrap(),
+                None => Rules::new(counter_account),
+            }
+            .with_classifier(classifier, f64::from(confidence) / 100.0);
+
+            let parsed = import::read(&statement, format, encoding, &csv).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                exit(1)
+            });
+            let imported = import::directives(&parsed, &account, &currency, &rules);
+            let total = imported.len();
+            let imported = import::skip_present(&directives, imported);
+            let rendered = import::render(&imported).unwrap();
+
+            eprintln!(
+                "imported {} directives from {}, skipping {} already in the ledger",
+                imported.len(),
+                statement.to_string_lossy().bold().green(),
+                total - imported.len()
+            );
+
+            if let Some(append) = append {
+                let mut file = OpenOptions::new().append(true).open(&append).unwrap();
+                write!(file, "\n{}", rendered).unwrap();
+            } else {
+                print!("{}", rendered);
+            }
+        }
+        Commands::RenumberAppendices { dry_run } => {
+            let renumbering = Renumbering::plan(&directives);
+
+            if renumbering.statements.is_empty() {
+                println!("appendices are already numbered sequentially");
+                exit(0)
+            }
+
+            if dry_run {
+                print!("{}", renumbering.diff());
+            } else {
+                renumbering.apply().unwrap();
+                println!("renumbered {} appendices", renumbering.statements.len());
+            }
+        }
+        Commands::Fmt {
+            column,
+            indent,
+            check,
+        } => {
+            let edits = format_ledger(&ledger, &FormatOptions { column, indent });
+
+            if check {
+                for edit in &edits {
+                    print!("{}", edit.diff());
+                }
+
+                if !edits.is_empty() {
+                    eprintln!("{} files are not formatted", edits.len());
+                    exit(1);
+                }
+            } else {
+                for edit in &edits {
+                    edit.apply().unwrap();
+                }
+                eprintln!("formatted {} files", edits.len());
+            }
+        }
+        Commands::Reconcile {
+            account,
+            statement,
+            format,
+            encoding,
+            currency,
+            window,
+            csv,
+        } => {
+            let account = parse_account(&account).unwrap();
+            let parsed = import::read(&statement, format, encoding, &csv).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                exit(1)
+            });
+            let reconciliation = reconcile(&directives, &account, &currency, &parsed, window);
+
+            print!("{}", reconciliation);
+            exit(if reconciliation.is_reconciled() { 0 } else { 1 })
+        }
+        Commands::Categorize {
+            uncategorized,
+            confidence,
+            dry_run,
+        } => {
+            let uncategorized = parse_account(&uncategorized).unwrap();
+            let classifier = Classifier::learn(&directives, &uncategorized);
+            let edits = categorize_ledger(
+                &ledger,
+                &directives,
+                &classifier,
+                &uncategorized,
+                f64::from(confidence) / 100.0,
+            );
+
+            if dry_run {
+                for edit in &edits {
+                    print!("{}", edit.diff());
+                }
+            } else {
+                for edit in &edits {
+                    edit.apply().unwrap();
+                }
+                eprintln!("categorized transactions in {} files", edits.len());
+            }
+        }
+        Commands::Sort { order, dry_run } => {
+            let edits = sort_ledger(&ledger, &directives, order);
+
+            if dry_run {
+                for edit in &edits {
+                    print!("{}", edit.diff());
                 }
-                println!("");
+            } else {
+                for edit in &edits {
+                    edit.apply().unwrap();
+                }
+                eprintln!("sorted {} files", edits.len());
             }
         }
         Commands::Balance {
             up_to_and_including,
             style,
         } => {
-            let table = apply_style(balance::balance(&ledger, up_to_and_including), style);
+            let table = balance::balance(&directives, up_to_and_including).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                exit(1)
+            });
+            let table = apply_style(table, style);
 
             println!("{}", table);
         }
+        Commands::Register {
+            account,
+            from,
+            to,
+            style,
+        } => {
+            let lines = register(&directives, &account_glob(&account), from, to);
+            println!("{}", apply_style(register::table(&lines), style));
+        }
+        Commands::Trend {
+            period,
+            account,
+            depth,
+            csv,
+            style,
+        } => {
+            let trend =
+                trend(&directives, period, account.as_deref(), depth).unwrap_or_else(|err| {
+                    eprintln!("{}", err);
+                    exit(1)
+                });
+
+            if csv {
+                print!("{}", trend.csv().unwrap());
+            } else {
+                println!("{}", apply_style(trend.table(), style));
+            }
+        }
+        Commands::Budget {
+            period,
+            from,
+            to,
+            style,
+        } => {
+            let budgets = budgets(&directives);
+            let Some(from) = from.or(budgets.first().map(|budget| budget.date)) else {
+                eprintln!("the ledger does not define any budgets");
+                exit(1)
+            };
+            let to = to.unwrap_or_else(|| Local::now().date_naive());
+
+            let variances = budget::report(&directives, &budgets, period, from, to);
+            println!("{}", apply_style(budget::table(&variances), style));
+        }
+        Commands::Query { query: text, style } => {
+            let text = query::named(&directives, &text).unwrap_or(&text);
+            match query::run(&directives, text) {
+                Ok(results) => println!("{}", apply_style(results.table(), style)),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    exit(1)
+                }
+            }
+        }
         Commands::AnnualAccounts { year, style } => {
-            let (table, statements) = annual::accounts(&ledger, year);
+            let (table, statements) = annual::accounts(&directives, year).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                exit(1)
+            });
             let table = apply_style(table, style);
 
             let output_path = PathBuf::from(year.to_string());
@@ -183,8 +804,9 @@ fn main() {
                 std::fs::copy(&statement, destination).unwrap();
             }
 
-            let initial_balance = apply_style(balance(&ledger, Some(year - 1)), style);
-            let final_balance = apply_style(balance(&ledger, Some(year)), style);
+            // The same transactions were itemized without error by `annual::accounts`.
+            let initial_balance = apply_style(balance(&directives, Some(year - 1)).unwrap(), style);
+            let final_balance = apply_style(balance(&directives, Some(year)).unwrap(), style);
 
             std::fs::write(
                 output_path.join("startsaldo.txt"),
@@ -0,0 +1,478 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+};
+
+use chrono::{Datelike, NaiveDate};
+use regex::Regex;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use crate::{amount::ResolvedPosting, readable::account_name};
+
+use super::parse::{Aggregate, Column, Expr, Function, Operator, Query};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(Decimal),
+    Text(String),
+    Date(NaiveDate),
+}
+
+impl Value {
+    fn is_true(&self) -> bool {
+        matches!(self, Value::Bool(true))
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Bool(value) => write!(f, "{}", value.to_string().to_uppercase()),
+            Value::Number(value) => write!(f, "{}", value),
+            Value::Text(value) => write!(f, "{}", value),
+            Value::Date(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// A transaction, with the amount of each of its postings resolved.
+#[derive(Debug)]
+pub struct Entry<'a> {
+    pub date: Option<NaiveDate>,
+    pub payee: Option<&'a str>,
+    pub narration: &'a str,
+    pub statement: Option<&'a str>,
+    pub postings: Vec<ResolvedPosting<'a>>,
+}
+
+/// A single posting, along with the transaction it belongs to.
+#[derive(Debug, Clone)]
+pub struct Row<'a> {
+    entry: &'a Entry<'a>,
+    posting: &'a ResolvedPosting<'a>,
+    /// Running total of the postings selected so far in the currency of this one.
+    balance: Decimal,
+}
+
+impl<'a> Row<'a> {
+    fn column(&self, column: Column) -> Value {
+        let text =
+            |text: Option<&str>| text.map_or(Value::Null, |text| Value::Text(text.to_string()));
+        let date = |f: fn(&NaiveDate) -> u32| {
+            self.entry
+                .date
+                .map_or(Value::Null, |date| Value::Number(f(&date).into()))
+        };
+
+        match column {
+            Column::Date => self.entry.date.map_or(Value::Null, Value::Date),
+            Column::Year => self
+                .entry
+                .date
+                .map_or(Value::Null, |date| Value::Number(date.year().into())),
+            Column::Month => date(NaiveDate::month),
+            Column::Day => date(NaiveDate::day),
+            Column::Payee => text(self.entry.payee),
+            Column::Narration => Value::Text(self.entry.narration.to_string()),
+            Column::Statement => text(self.entry.statement),
+            Column::Account => Value::Text(account_name(self.posting.account)),
+            Column::Amount => Value::Number(self.posting.amount),
+            Column::Currency => text(self.posting.currency),
+            Column::Balance => Value::Number(self.balance),
+        }
+    }
+}
+
+/// Patterns of the `~` operator, compiled once per query rather than once per row.
+#[derive(Debug, Default)]
+struct Patterns(HashMap<String, Regex>);
+
+impl Patterns {
+    /// Compile the literal patterns within `expr`.
+    fn compile(&mut self, expr: &Expr) -> Result<(), String> {
+        match expr {
+            Expr::Binary(operator, left, right) => {
+                if let (Operator::Matches, Expr::Literal(Value::Text(pattern))) =
+                    (operator, right.as_ref())
+                {
+                    if !self.0.contains_key(pattern) {
+                        let regex = Regex::new(pattern).map_err(|err| err.to_string())?;
+                        self.0.insert(pattern.clone(), regex);
+                    }
+                }
+
+                self.compile(left)?;
+                self.compile(right)
+            }
+            Expr::Not(inner) | Expr::Negate(inner) => self.compile(inner),
+            Expr::Function(_, arguments) => arguments
+                .iter()
+                .try_for_each(|argument| self.compile(argument)),
+            Expr::Aggregate(_, Some(argument)) => self.compile(argument),
+            Expr::Literal(_) | Expr::Column(_) | Expr::Alias(_) | Expr::Aggregate(_, None) => {
+                Ok(())
+            }
+        }
+    }
+
+    fn matches(&self, text: &str, pattern: &str) -> Result<bool, String> {
+        match self.0.get(pattern) {
+            Some(regex) => Ok(regex.is_match(text)),
+            // Patterns computed from the rows are compiled as they come.
+            None => Ok(Regex::new(pattern)
+                .map_err(|err| err.to_string())?
+                .is_match(text)),
+        }
+    }
+}
+
+fn binary(
+    operator: Operator,
+    left: Value,
+    right: Value,
+    patterns: &Patterns,
+) -> Result<Value, String> {
+    use Value::*;
+
+    // Dates may also be compared to quoted dates.
+    let date = |text: &str| {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .map(Date)
+            .map_err(|_| format!("cannot compare a date to {}", text))
+    };
+
+    let (left, right) = match (left, right) {
+        (Date(left), Text(right)) => (Date(left), date(&right)?),
+        (Text(left), Date(right)) => (date(&left)?, Date(right)),
+        (left, right) => (left, right),
+    };
+
+    let mismatch = |left: &Value, right: &Value| {
+        format!("cannot apply {:?} to {} and {}", operator, left, right)
+    };
+
+    Ok(match (operator, left, right) {
+        (Operator::And, left, right) => Bool(left.is_true() && right.is_true()),
+        (Operator::Or, left, right) => Bool(left.is_true() || right.is_true()),
+        (_, Null, _) | (_, _, Null) => Null,
+        (Operator::Equal, left, right) => Bool(left == right),
+        (Operator::NotEqual, left, right) => Bool(left != right),
+        (Operator::Matches, Text(text), Text(pattern)) => Bool(patterns.matches(&text, &pattern)?),
+        (
+            Operator::Less | Operator::LessOrEqual | Operator::Greater | Operator::GreaterOrEqual,
+            left,
+            right,
+        ) => {
+            if std::mem::discriminant(&left) != std::mem::discriminant(&right) {
+                return Err(mismatch(&left, &right));
+            }
+
+            Bool(match operator {
+                Operator::Less => left < right,
+                Operator::LessOrEqual => left <= right,
+                Operator::Greater => left > right,
+                _ => left >= right,
+            })
+        }
+        (Operator::Add, Number(left), Number(right)) => Number(left + right),
+        (Operator::Subtract, Number(left), Number(right)) => Number(left - right),
+        (Operator::Multiply, Number(left), Number(right)) => Number(left * right),
+        (Operator::Divide, Number(left), Number(right)) => Number(
+            left.checked_div(right)
+                .ok_or_else(|| format!("cannot divide {} by {}", left, right))?,
+        ),
+        (_, left, right) => return Err(mismatch(&left, &right)),
+    })
+}
+
+fn function(function: Function, arguments: Vec<Value>) -> Result<Value, String> {
+    use Value::*;
+
+    Ok(match (function, arguments.as_slice()) {
+        (_, [Null, ..]) => Null,
+        (Function::Year, [Date(date)]) => Number(date.year().into()),
+        (Function::Month, [Date(date)]) => Number(date.month().into()),
+        (Function::Day, [Date(date)]) => Number(date.day().into()),
+        (Function::Abs, [Number(number)]) => Number(number.abs()),
+        (Function::Lower, [Text(text)]) => Text(text.to_lowercase()),
+        (Function::Upper, [Text(text)]) => Text(text.to_uppercase()),
+        (Function::Root, [Text(account), Number(depth)]) => {
+            let depth = depth.to_usize().unwrap_or_default();
+            Text(account.split(':').take(depth).collect::<Vec<_>>().join(":"))
+        }
+        (Function::Parent, [Text(account)]) => Text(
+            account
+                .rsplit_once(':')
+                .map_or(account.as_str(), |(parent, _)| parent)
+                .to_string(),
+        ),
+        (function, arguments) => {
+            let arguments: Vec<_> = arguments.iter().map(ToString::to_string).collect();
+            return Err(format!(
+                "cannot apply {:?} to {}",
+                function,
+                arguments.join(", ")
+            ));
+        }
+    })
+}
+
+/// Evaluate the expression against a group of rows. Aggregates range over every
+/// row of the group, while everything else refers to its first row.
+fn evaluate(expr: &Expr, group: &[Row], patterns: &Patterns) -> Result<Value, String> {
+    Ok(match expr {
+        Expr::Literal(value) => value.clone(),
+        Expr::Column(column) => group.first().map_or(Value::Null, |row| row.column(*column)),
+        Expr::Alias(name) => return Err(format!("unknown column {}", name)),
+        Expr::Not(inner) => match evaluate(inner, group, patterns)? {
+            Value::Null => Value::Null,
+            value => Value::Bool(!value.is_true()),
+        },
+        Expr::Negate(inner) => match evaluate(inner, group, patterns)? {
+            Value::Number(number) => Value::Number(-number),
+            Value::Null => Value::Null,
+            value => return Err(format!("cannot negate {}", value)),
+        },
+        Expr::Binary(operator, left, right) => binary(
+            *operator,
+            evaluate(left, group, patterns)?,
+            evaluate(right, group, patterns)?,
+            patterns,
+        )?,
+        Expr::Function(name, arguments) => function(
+            *name,
+            arguments
+                .iter()
+                .map(|argument| evaluate(argument, group, patterns))
+                .collect::<Result<_, _>>()?,
+        )?,
+        Expr::Aggregate(aggregate, argument) => {
+            let mut values = Vec::new();
+            let mut currencies = BTreeSet::new();
+            for row in group {
+                let value = match argument {
+                    Some(argument) => evaluate(argument, std::slice::from_ref(row), patterns)?,
+                    None => Value::Bool(true),
+                };
+
+                if value != Value::Null {
+                    values.push(value);
+                    currencies.insert(row.posting.currency.unwrap_or_default());
+                }
+            }
+
+            match aggregate {
+                Aggregate::Count => Value::Number(values.len().into()),
+                Aggregate::Sum if currencies.len() > 1 => {
+                    return Err(format!(
+                        "cannot sum amounts in {}, group by currency",
+                        currencies.into_iter().collect::<Vec<_>>().join(" and ")
+                    ))
+                }
+                Aggregate::Sum => Value::Number(
+                    values
+                        .iter()
+                        .map(|value| match value {
+                            Value::Number(number) => Ok(*number),
+                            value => Err(format!("cannot sum {}", value)),
+                        })
+                        .sum::<Result<Decimal, _>>()?,
+                ),
+                Aggregate::Min => values.into_iter().min().unwrap_or(Value::Null),
+                Aggregate::Max => values.into_iter().max().unwrap_or(Value::Null),
+                Aggregate::First => values.into_iter().next().unwrap_or(Value::Null),
+                Aggregate::Last => values.into_iter().last().unwrap_or(Value::Null),
+            }
+        }
+    })
+}
+
+/// Replace references to the targets of the query, by name or by their
+/// position counting from one, with the expressions of those targets.
+fn resolve(expr: &Expr, query: &Query) -> Expr {
+    let target = |name: &str| {
+        query
+            .targets
+            .iter()
+            .find(|target| target.name == name)
+            .map(|target| target.expr.clone())
+    };
+
+    let resolved = match expr {
+        Expr::Literal(Value::Number(position)) => position
+            .to_usize()
+            .and_then(|position| position.checked_sub(1))
+            .and_then(|index| query.targets.get(index))
+            .map(|target| target.expr.clone()),
+        Expr::Alias(name) => target(name),
+        Expr::Column(column) => target(column.name()),
+        _ => None,
+    };
+
+    resolved.unwrap_or_else(|| expr.clone())
+}
+
+/// Header and rows produced by a query.
+#[derive(Debug, Default, PartialEq)]
+pub struct Results {
+    pub names: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+pub fn execute(query: &Query, entries: &[Entry]) -> Result<Results, String> {
+    let mut patterns = Patterns::default();
+    for expr in query
+        .from
+        .iter()
+        .chain(&query.filter)
+        .chain(query.targets.iter().map(|target| &target.expr))
+        .chain(&query.group_by)
+        .chain(query.order_by.iter().map(|ordering| &ordering.expr))
+    {
+        patterns.compile(expr)?;
+    }
+    let patterns = &patterns;
+
+    let mut rows = Vec::new();
+    let mut balances: HashMap<Option<&str>, Decimal> = HashMap::new();
+
+    for entry in entries {
+        let candidates: Vec<_> = entry
+            .postings
+            .iter()
+            .map(|posting| Row {
+                entry,
+                posting,
+                balance: Decimal::ZERO,
+            })
+            .collect();
+
+        // The transaction is kept as a whole if any of its postings match.
+        if let Some(from) = &query.from {
+            let mut kept = false;
+            for row in &candidates {
+                kept |= evaluate(from, std::slice::from_ref(row), patterns)?.is_true();
+            }
+
+            if !kept {
+                continue;
+            }
+        }
+
+        for mut row in candidates {
+            let balance = balances.entry(row.posting.currency).or_default();
+            row.balance = *balance + row.posting.amount;
+
+            if let Some(filter) = &query.filter {
+                if !evaluate(filter, std::slice::from_ref(&row), patterns)?.is_true() {
+                    continue;
+                }
+            }
+
+            *balance = row.balance;
+            rows.push(row);
+        }
+    }
+
+    let group_by: Vec<_> = query
+        .group_by
+        .iter()
+        .map(|expr| resolve(expr, query))
+        .collect();
+
+    let aggregated = !group_by.is_empty() || query.targets.iter().any(|t| t.expr.is_aggregate());
+
+    let groups: Vec<Vec<Row>> = if aggregated {
+        // Without an explicit grouping, rows are grouped by the targets which
+        // are not aggregates, like in beancount.
+        let keys = if group_by.is_empty() {
+            query
+                .targets
+                .iter()
+                .map(|target| target.expr.clone())
+                .filter(|expr| !expr.is_aggregate())
+                .collect()
+        } else {
+            group_by
+        };
+
+        let mut groups: Vec<Vec<Row>> = Vec::new();
+        let mut indices = HashMap::new();
+
+        if keys.is_empty() {
+            groups.push(rows);
+        } else {
+            for row in rows {
+                let key = keys
+                    .iter()
+                    .map(|key| evaluate(key, std::slice::from_ref(&row), patterns))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let index = *indices.entry(key).or_insert_with(|| {
+                    groups.push(Vec::new());
+                    groups.len() - 1
+                });
+                groups[index].push(row);
+            }
+        }
+
+        groups
+    } else {
+        rows.into_iter().map(|row| vec![row]).collect()
+    };
+
+    let order_by: Vec<_> = query
+        .order_by
+        .iter()
+        .map(|ordering| (resolve(&ordering.expr, query), ordering.descending))
+        .collect();
+
+    let mut results = Vec::new();
+    for group in &groups {
+        let values = query
+            .targets
+            .iter()
+            .map(|target| evaluate(&target.expr, group, patterns))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let keys = order_by
+            .iter()
+            .map(|(expr, _)| evaluate(expr, group, patterns))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        results.push((keys, values));
+    }
+
+    results.sort_by(|(a, _), (b, _)| {
+        a.iter()
+            .zip(b)
+            .zip(&order_by)
+            .map(
+                |((a, b), (_, descending))| {
+                    if *descending {
+                        b.cmp(a)
+                    } else {
+                        a.cmp(b)
+                    }
+                },
+            )
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    Ok(Results {
+        names: query
+            .targets
+            .iter()
+            .map(|target| target.name.clone())
+            .collect(),
+        rows: results
+            .into_iter()
+            .map(|(_, values)| values)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect(),
+    })
+}
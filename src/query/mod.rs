@@ -0,0 +1,186 @@
+mod eval;
+mod parse;
+
+use beancount_core::{metadata::MetaValue, Directive, Query};
+use log::debug;
+use tabled::{builder::Builder, Table};
+
+use crate::{amount::resolved_postings, date::naive_date, error::Error, ledger::Sourced};
+
+use eval::Entry;
+pub use eval::{Results, Value};
+
+/// Transactions of the ledger in date order. Fails if the amount of a posting
+/// cannot be inferred, rather than leaving it out of every sum.
+fn entries<'a>(directives: &'a [Sourced<'a, Directive<'a>>]) -> Result<Vec<Entry<'a>>, Error> {
+    let mut entries = Vec::new();
+
+    for directive in directives {
+        let Directive::Transaction(txn) = &directive.inner else {
+            continue;
+        };
+
+        let postings = resolved_postings(txn);
+        if postings.len() != txn.postings.len() {
+            return Err(Error::Query(format!(
+                "cannot infer the amount of every posting of the transaction at {}:{}",
+                directive.location.ledger().filename().to_string_lossy(),
+                directive.location.start() + 1 + directive.location.ledger().line_offset(),
+            )));
+        }
+
+        entries.push(Entry {
+            date: naive_date(&txn.date),
+            payee: txn.payee.as_deref(),
+            narration: &txn.narration,
+            statement: match txn.meta.get("statement") {
+                Some(MetaValue::Text(statement)) => Some(statement.as_ref()),
+                _ => None,
+            },
+            postings,
+        });
+    }
+
+    entries.sort_by_key(|entry| entry.date);
+    Ok(entries)
+}
+
+/// Run a query written in a subset of the beancount query language:
+///
+/// ```text
+/// SELECT account, sum(amount) AS total
+/// FROM year = 2023
+/// WHERE account ~ '^Expenses'
+/// GROUP BY account
+/// ORDER BY total DESC
+/// LIMIT 10
+/// ```
+///
+/// or `BALANCES [FROM ...] [WHERE ...]` for the balance of every account.
+/// Each row is a posting, with the columns `date`, `year`, `month`, `day`,
+/// `payee`, `narration`, `statement`, `account`, `amount`, `currency` and the
+/// running `balance` of the selected postings. Amounts are never converted
+/// between currencies, so summing postings in several currencies is an error.
+pub fn run(directives: &[Sourced<Directive>], query: &str) -> Result<Results, Error> {
+    let parsed = parse::parse(query).map_err(Error::Query)?;
+    debug!("parsed query {:?}", parsed);
+
+    let entries = entries(directives)?;
+    eval::execute(&parsed, &entries).map_err(Error::Query)
+}
+
+/// Query string of the `query` directive with this name, if any.
+pub fn named<'a>(directives: &'a [Sourced<'a, Directive<'a>>], name: &str) -> Option<&'a str> {
+    directives
+        .iter()
+        .find_map(|directive| match &directive.inner {
+            Directive::Query(Query {
+                name: query_name,
+                query_string,
+                ..
+            }) if query_name == name => Some(query_string.as_ref()),
+            _ => None,
+        })
+}
+
+impl Results {
+    pub fn table(&self) -> Table {
+        let mut builder = Builder::new();
+        builder.push_record(self.names.iter().cloned());
+
+        for row in &self.rows {
+            builder.push_record(row.iter().map(ToString::to_string));
+        }
+
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::{named, run, Value};
+    use crate::inline_ledger;
+
+    #[test]
+    fn test_query() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 query "food" "SELECT sum(amount) WHERE account = 'Expenses:Food'"
+
+        2000-01-02 * "Netto" ""
+            statement: "documents/2000-01-02.1.netto.pdf"
+            Assets:Bank:Account  -150 DKK
+            Expenses:Food
+
+        2000-01-03 * "DSB" ""
+            statement: "documents/2000-01-03.2.dsb.pdf"
+            Assets:Bank:Account  -400 DKK
+            Expenses:Travel
+
+        2000-02-01 * "Netto" ""
+            statement: "documents/2000-02-01.3.netto.pdf"
+            Assets:Bank:Account  -50 DKK
+            Expenses:Food
+
+        2000-03-01 * "Librairie" ""
+            Assets:Bank:Euro  -10 EUR
+            Expenses:Books
+        "#
+        );
+
+        let directives = ledger.directives();
+
+        let results = run(
+            &directives,
+            "SELECT month, sum(amount) AS total WHERE account ~ '^Expenses' ORDER BY total DESC",
+        )
+        .unwrap();
+        assert_eq!(results.names, vec!["month", "total"]);
+        assert_eq!(
+            results.rows,
+            vec![
+                vec![Value::Number(1.into()), Value::Number(Decimal::new(550, 0))],
+                vec![Value::Number(2.into()), Value::Number(Decimal::new(50, 0))],
+                vec![Value::Number(3.into()), Value::Number(Decimal::new(10, 0))],
+            ]
+        );
+        println!("{}", results.table());
+
+        let balances = run(&directives, "BALANCES").unwrap();
+        assert_eq!(balances.rows.len(), 5);
+        assert_eq!(
+            balances.rows[0][0],
+            Value::Text("Assets:Bank:Account".to_string())
+        );
+        assert_eq!(balances.rows[0][1], Value::Number(Decimal::new(-600, 0)));
+        assert_eq!(balances.rows[1][2], Value::Text("EUR".to_string()));
+
+        // Transactions without a statement have none, rather than failing the query.
+        let statements = run(
+            &directives,
+            "SELECT statement WHERE account = 'Expenses:Books'",
+        );
+        assert_eq!(statements.unwrap().rows, vec![vec![Value::Null]]);
+
+        // Amounts in different currencies are not added up.
+        assert!(run(
+            &directives,
+            "SELECT sum(amount) WHERE account ~ '^Expenses'"
+        )
+        .is_err());
+        let totals = run(
+            &directives,
+            "SELECT currency, sum(amount) WHERE account ~ '^Expenses' GROUP BY currency ORDER BY currency",
+        )
+        .unwrap();
+        assert_eq!(totals.rows.len(), 2);
+
+        // Invalid patterns are reported, rather than panicking.
+        assert!(run(&directives, "SELECT account WHERE payee ~ '('").is_err());
+
+        let food = run(&directives, named(&directives, "food").unwrap()).unwrap();
+        assert_eq!(food.rows, vec![vec![Value::Number(Decimal::new(200, 0))]]);
+    }
+}
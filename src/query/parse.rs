@@ -0,0 +1,623 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::NaiveDate;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use super::eval::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(Decimal),
+    Text(String),
+    Date(NaiveDate),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 16] = [
+    "!=", "<>", "<=", ">=", "(", ")", ",", ";", "*", "+", "-", "/", "=", "<", ">", "~",
+];
+
+fn tokenize(query: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = query.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let length = if c == '\'' || c == '"' {
+            let end = rest[1..]
+                .find(c)
+                .ok_or_else(|| format!("unterminated string {}", rest))?;
+            tokens.push(Token::Text(rest[1..=end].to_string()));
+            end + 2
+        } else if c.is_ascii_digit() {
+            let length = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+                .unwrap_or(rest.len());
+            let literal = &rest[..length];
+
+            // Dates are written like in the ledger, without quotes.
+            if let Ok(date) = NaiveDate::parse_from_str(literal, "%Y-%m-%d") {
+                tokens.push(Token::Date(date));
+                length
+            } else {
+                let length = literal.find('-').unwrap_or(length);
+                let number = Decimal::from_str(&literal[..length])
+                    .map_err(|_| format!("invalid number {}", &literal[..length]))?;
+                tokens.push(Token::Number(number));
+                length
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let length = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Word(rest[..length].to_string()));
+            length
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(*symbol))
+                .ok_or_else(|| format!("unexpected character {}", c))?;
+            tokens.push(Token::Symbol(*symbol));
+            symbol.len()
+        };
+
+        rest = rest[length..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// Attributes of a posting, and the transaction it belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Date,
+    Year,
+    Month,
+    Day,
+    Payee,
+    Narration,
+    Statement,
+    Account,
+    Amount,
+    Currency,
+    /// Running total of the amounts of the postings selected so far, in the
+    /// currency of the posting.
+    Balance,
+}
+
+impl Column {
+    const ALL: [Column; 11] = [
+        Column::Date,
+        Column::Year,
+        Column::Month,
+        Column::Day,
+        Column::Payee,
+        Column::Narration,
+        Column::Statement,
+        Column::Account,
+        Column::Amount,
+        Column::Currency,
+        Column::Balance,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Date => "date",
+            Column::Year => "year",
+            Column::Month => "month",
+            Column::Day => "day",
+            Column::Payee => "payee",
+            Column::Narration => "narration",
+            Column::Statement => "statement",
+            Column::Account => "account",
+            Column::Amount => "amount",
+            Column::Currency => "currency",
+            Column::Balance => "balance",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Year,
+    Month,
+    Day,
+    Abs,
+    Lower,
+    Upper,
+    /// The first n components of an account name.
+    Root,
+    /// The account name without its last component.
+    Parent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum,
+    Count,
+    Min,
+    Max,
+    First,
+    Last,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Matches,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl Operator {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Or => "OR",
+            Operator::And => "AND",
+            Operator::Equal => "=",
+            Operator::NotEqual => "!=",
+            Operator::Less => "<",
+            Operator::LessOrEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterOrEqual => ">=",
+            Operator::Matches => "~",
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    Column(Column),
+    /// Reference to a target of the query by its name.
+    Alias(String),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+    Function(Function, Vec<Expr>),
+    /// An aggregate over the rows of a group, `None` standing for `*`.
+    Aggregate(Aggregate, Option<Box<Expr>>),
+}
+
+impl Expr {
+    pub fn is_aggregate(&self) -> bool {
+        match self {
+            Expr::Aggregate(..) => true,
+            Expr::Literal(_) | Expr::Column(_) | Expr::Alias(_) => false,
+            Expr::Not(inner) | Expr::Negate(inner) => inner.is_aggregate(),
+            Expr::Binary(_, left, right) => left.is_aggregate() || right.is_aggregate(),
+            Expr::Function(_, arguments) => arguments.iter().any(Expr::is_aggregate),
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Literal(value) => write!(f, "{}", value),
+            Expr::Column(column) => write!(f, "{}", column.name()),
+            Expr::Alias(name) => write!(f, "{}", name),
+            Expr::Not(inner) => write!(f, "NOT {}", inner),
+            Expr::Negate(inner) => write!(f, "-{}", inner),
+            Expr::Binary(operator, left, right) => {
+                write!(f, "{} {} {}", left, operator.symbol(), right)
+            }
+            Expr::Function(function, arguments) => {
+                let arguments: Vec<_> = arguments.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "{}({})",
+                    format!("{:?}", function).to_lowercase(),
+                    arguments.join(", ")
+                )
+            }
+            Expr::Aggregate(aggregate, argument) => write!(
+                f,
+                "{}({})",
+                format!("{:?}", aggregate).to_lowercase(),
+                argument
+                    .as_ref()
+                    .map_or("*".to_string(), |argument| argument.to_string())
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub expr: Expr,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ordering {
+    pub expr: Expr,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Query {
+    pub targets: Vec<Target>,
+    /// Filter on the transactions, before the `WHERE` clause filters individual postings.
+    pub from: Option<Expr>,
+    pub filter: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub order_by: Vec<Ordering>,
+    pub limit: Option<usize>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        match self.peek() {
+            Some(Token::Symbol(s)) if *s == symbol => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        self.keyword(keyword)
+            .then_some(())
+            .ok_or_else(|| format!("expected {}", keyword))
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        self.symbol(symbol)
+            .then_some(())
+            .ok_or_else(|| format!("expected {}", symbol))
+    }
+
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        let mut items = vec![item(self)?];
+        while self.symbol(",") {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn binary(
+        &mut self,
+        operators: &[(&str, Operator)],
+        mut operand: impl FnMut(&mut Self) -> Result<Expr, String>,
+    ) -> Result<Expr, String> {
+        let mut left = operand(self)?;
+
+        'outer: loop {
+            for (token, operator) in operators {
+                let matched = if token.chars().all(char::is_alphabetic) {
+                    self.keyword(token)
+                } else {
+                    self.symbol(token)
+                };
+
+                if matched {
+                    left = Expr::Binary(*operator, Box::new(left), Box::new(operand(self)?));
+                    continue 'outer;
+                }
+            }
+
+            return Ok(left);
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        self.binary(&[("OR", Operator::Or)], |parser| {
+            parser.binary(&[("AND", Operator::And)], Self::negation)
+        })
+    }
+
+    fn negation(&mut self) -> Result<Expr, String> {
+        if self.keyword("NOT") {
+            Ok(Expr::Not(Box::new(self.negation()?)))
+        } else {
+            self.comparison()
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        self.binary(
+            &[
+                ("=", Operator::Equal),
+                ("!=", Operator::NotEqual),
+                ("<>", Operator::NotEqual),
+                ("<=", Operator::LessOrEqual),
+                (">=", Operator::GreaterOrEqual),
+                ("<", Operator::Less),
+                (">", Operator::Greater),
+                ("~", Operator::Matches),
+            ],
+            |parser| {
+                parser.binary(
+                    &[("+", Operator::Add), ("-", Operator::Subtract)],
+                    |parser| {
+                        parser.binary(
+                            &[("*", Operator::Multiply), ("/", Operator::Divide)],
+                            Self::unary,
+                        )
+                    },
+                )
+            },
+        )
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.symbol("-") {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expr::Literal(Value::Number(number))),
+            Some(Token::Text(text)) => Ok(Expr::Literal(Value::Text(text))),
+            Some(Token::Date(date)) => Ok(Expr::Literal(Value::Date(date))),
+            Some(Token::Symbol("(")) => {
+                let expr = self.expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Some(Token::Word(word)) if self.symbol("(") => self.call(&word),
+            Some(Token::Word(word)) => match word.to_lowercase().as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                // Beancount calls the amount of a posting its position.
+                "position" | "number" => Ok(Expr::Column(Column::Amount)),
+                name => Ok(Column::ALL
+                    .into_iter()
+                    .find(|column| column.name() == name)
+                    .map_or(Expr::Alias(word), Expr::Column)),
+            },
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of query".to_string()),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<Expr, String> {
+        let aggregate = match name.to_lowercase().as_str() {
+            "sum" => Some(Aggregate::Sum),
+            "count" => Some(Aggregate::Count),
+            "min" => Some(Aggregate::Min),
+            "max" => Some(Aggregate::Max),
+            "first" => Some(Aggregate::First),
+            "last" => Some(Aggregate::Last),
+            _ => None,
+        };
+
+        if let Some(aggregate) = aggregate {
+            let argument = if self.symbol("*") {
+                None
+            } else {
+                Some(Box::new(self.expr()?))
+            };
+            self.expect_symbol(")")?;
+
+            return Ok(Expr::Aggregate(aggregate, argument));
+        }
+
+        let (function, arity) = match name.to_lowercase().as_str() {
+            "year" => (Function::Year, 1),
+            "month" => (Function::Month, 1),
+            "day" => (Function::Day, 1),
+            "abs" => (Function::Abs, 1),
+            "lower" => (Function::Lower, 1),
+            "upper" => (Function::Upper, 1),
+            "root" => (Function::Root, 2),
+            "parent" => (Function::Parent, 1),
+            _ => return Err(format!("unknown function {}", name)),
+        };
+
+        let arguments = if self.symbol(")") {
+            Vec::new()
+        } else {
+            let arguments = self.list(Self::expr)?;
+            self.expect_symbol(")")?;
+            arguments
+        };
+
+        if arguments.len() != arity {
+            return Err(format!("{} takes {} arguments", name, arity));
+        }
+
+        Ok(Expr::Function(function, arguments))
+    }
+
+    fn target(&mut self) -> Result<Vec<Target>, String> {
+        if self.symbol("*") {
+            return Ok(Column::ALL
+                .into_iter()
+                .map(|column| Target {
+                    expr: Expr::Column(column),
+                    name: column.name().to_string(),
+                })
+                .collect());
+        }
+
+        let expr = self.expr()?;
+        let name = if self.keyword("AS") {
+            match self.next() {
+                Some(Token::Word(name) | Token::Text(name)) => name,
+                _ => return Err("expected a name after AS".to_string()),
+            }
+        } else {
+            expr.to_string()
+        };
+
+        Ok(vec![Target { expr, name }])
+    }
+
+    fn ordering(&mut self) -> Result<Ordering, String> {
+        let expr = self.expr()?;
+        let descending = self.keyword("DESC");
+        if !descending {
+            self.keyword("ASC");
+        }
+
+        Ok(Ordering { expr, descending })
+    }
+
+    /// Clauses following the targets, shared by `SELECT` and `BALANCES`.
+    fn clauses(&mut self, query: &mut Query) -> Result<(), String> {
+        if self.keyword("FROM") {
+            query.from = Some(self.expr()?);
+        }
+
+        if self.keyword("WHERE") {
+            query.filter = Some(self.expr()?);
+        }
+
+        Ok(())
+    }
+
+    fn query(&mut self) -> Result<Query, String> {
+        let mut query = Query::default();
+
+        if self.keyword("BALANCES") {
+            // Shorthand for the balance of every account.
+            self.clauses(&mut query)?;
+            query.targets = vec![
+                Target {
+                    expr: Expr::Column(Column::Account),
+                    name: "account".to_string(),
+                },
+                Target {
+                    expr: Expr::Aggregate(
+                        Aggregate::Sum,
+                        Some(Box::new(Expr::Column(Column::Amount))),
+                    ),
+                    name: "balance".to_string(),
+                },
+                Target {
+                    expr: Expr::Column(Column::Currency),
+                    name: "currency".to_string(),
+                },
+            ];
+            query.group_by = vec![
+                Expr::Column(Column::Account),
+                Expr::Column(Column::Currency),
+            ];
+            query.order_by = [Column::Account, Column::Currency]
+                .into_iter()
+                .map(|column| Ordering {
+                    expr: Expr::Column(column),
+                    descending: false,
+                })
+                .collect();
+        } else {
+            self.expect_keyword("SELECT")?;
+            query.targets = self.list(Self::target)?.into_iter().flatten().collect();
+            self.clauses(&mut query)?;
+
+            if self.keyword("GROUP") {
+                self.expect_keyword("BY")?;
+                query.group_by = self.list(Self::expr)?;
+            }
+
+            if self.keyword("ORDER") {
+                self.expect_keyword("BY")?;
+                query.order_by = self.list(Self::ordering)?;
+            }
+        }
+
+        if self.keyword("LIMIT") {
+            match self.next() {
+                Some(Token::Number(limit)) => {
+                    query.limit = Some(
+                        limit
+                            .to_usize()
+                            .ok_or_else(|| format!("invalid limit {}", limit))?,
+                    )
+                }
+                _ => return Err("expected a number after LIMIT".to_string()),
+            }
+        }
+
+        self.symbol(";");
+        match self.peek() {
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Ok(query),
+        }
+    }
+}
+
+pub fn parse(query: &str) -> Result<Query, String> {
+    Parser {
+        tokens: tokenize(query)?,
+        position: 0,
+    }
+    .query()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Aggregate, Column, Expr, Operator};
+
+    #[test]
+    fn test_parse_query() {
+        let query = parse(
+            "select account, sum(position) as total \
+            where account ~ '^Expenses' and date >= 2000-01-01 \
+            group by account order by total desc limit 10",
+        )
+        .unwrap();
+
+        assert_eq!(query.targets.len(), 2);
+        assert_eq!(query.targets[0].name, "account");
+        assert_eq!(query.targets[1].name, "total");
+        assert!(matches!(
+            query.targets[1].expr,
+            Expr::Aggregate(Aggregate::Sum, Some(_))
+        ));
+        assert!(matches!(
+            query.filter,
+            Some(Expr::Binary(Operator::And, _, _))
+        ));
+        assert_eq!(query.group_by, vec![Expr::Column(Column::Account)]);
+        assert!(query.order_by[0].descending);
+        assert_eq!(query.limit, Some(10));
+
+        assert!(parse("select sum(amount").is_err());
+        assert!(parse("balances where year = 2000").is_ok());
+    }
+}
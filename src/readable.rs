@@ -1,10 +1,15 @@
 use std::fmt::Display;
 
-use beancount_core::Transaction;
+use beancount_core::{Account, Transaction};
 use colored::Colorize;
 
 use crate::ledger::Sourced;
 
+/// Render an account the way it's written in the ledger, e.g. `Assets:Bank:Account`.
+pub fn account_name(account: &Account) -> String {
+    format!("{:?}:{}", account.ty, account.parts.join(":"))
+}
+
 pub struct Payees(Vec<String>);
 
 impl<'a> From<&Transaction<'a>> for Payees {
@@ -0,0 +1,269 @@
+use std::fmt::Display;
+
+use beancount_core::{Account, Directive};
+use chrono::{Duration, NaiveDate};
+use colored::Colorize;
+use log::debug;
+use rust_decimal::Decimal;
+
+use crate::{
+    amount::resolved_postings,
+    date::naive_date,
+    import::{ClosingBalance, Entry, Statement},
+    ledger::Sourced,
+    location::Location,
+    readable::account_name,
+};
+
+/// A posting to the reconciled account.
+#[derive(Debug, Clone)]
+pub struct LedgerPosting<'a> {
+    pub date: NaiveDate,
+    pub payee: &'a str,
+    pub amount: Decimal,
+    pub location: Location<'a>,
+}
+
+#[derive(Debug)]
+pub struct Reconciliation<'a> {
+    pub matched: usize,
+    /// Statement lines without a corresponding posting in the ledger.
+    pub unmatched_statement: Vec<Entry>,
+    /// Postings within the period of the statement which are not on it.
+    pub unmatched_ledger: Vec<LedgerPosting<'a>>,
+    /// Closing balance of the statement, along with the balance of the
+    /// account in the ledger at the end of the same day.
+    pub closing: Option<(ClosingBalance, Decimal)>,
+}
+
+impl<'a> Reconciliation<'a> {
+    pub fn is_reconciled(&self) -> bool {
+        self.unmatched_statement.is_empty()
+            && self.unmatched_ledger.is_empty()
+            && self
+                .closing
+                .as_ref()
+                .map_or(true, |(closing, balance)| closing.amount == *balance)
+    }
+}
+
+/// Match the lines of the statement to postings to `account` in the given
+/// currency with the same amount, booked at most `window` days apart.
+pub fn reconcile<'a>(
+    directives: &'a [Sourced<'a, Directive<'a>>],
+    account: &Account,
+    currency: &str,
+    statement: &Statement,
+    window: u32,
+) -> Reconciliation<'a> {
+    debug!("reconciling {}", account_name(account));
+    let currency = statement.currency.as_deref().unwrap_or(currency);
+
+    let postings: Vec<_> = directives
+        .iter()
+        .filter_map(|directive| match &directive.inner {
+            Directive::Transaction(txn) => Some((directive, txn, naive_date(&txn.date)?)),
+            _ => None,
+        })
+        .flat_map(|(directive, txn, date)| {
+            resolved_postings(txn)
+                .into_iter()
+                .filter(|posting| posting.account == account)
+                .filter(|posting| posting.currency.unwrap_or(currency) == currency)
+                .map(move |posting| LedgerPosting {
+                    date,
+                    payee: txn.payee.as_deref().unwrap_or_default(),
+                    amount: posting.amount,
+                    location: directive.location.clone(),
+                })
+        })
+        .collect();
+
+    let mut entries = statement.entries.clone();
+    entries.sort_by_key(|entry| entry.date);
+
+    let first = entries.first().map(|entry| entry.date);
+    let last = statement
+        .closing_balance
+        .as_ref()
+        .map(|closing| closing.date)
+        .into_iter()
+        .chain(entries.last().map(|entry| entry.date))
+        .max();
+
+    // Postings booked up to `window` days outside the statement may still match
+    // one of its lines, but are only reported as missing when within its period.
+    let within = |posting: &LedgerPosting, slack: i64| {
+        first.is_some_and(|first| posting.date >= first - Duration::days(slack))
+            && last.is_some_and(|last| posting.date <= last + Duration::days(slack))
+    };
+
+    let mut unmatched: Vec<_> = postings
+        .iter()
+        .filter(|posting| within(posting, i64::from(window)))
+        .cloned()
+        .map(Some)
+        .collect();
+
+    let mut matched = 0;
+    let mut unmatched_statement = Vec::new();
+
+    for entry in entries {
+        // The closest booking with the same amount, within the window.
+        let candidate = unmatched
+            .iter()
+            .enumerate()
+            .filter_map(|(index, posting)| Some((index, posting.as_ref()?)))
+            .filter(|(_, posting)| posting.amount == entry.amount)
+            .map(|(index, posting)| (index, (posting.date - entry.date).num_days().abs()))
+            .filter(|(_, distance)| *distance <= i64::from(window))
+            .min_by_key(|(_, distance)| *distance);
+
+        match candidate {
+            Some((index, _)) => {
+                unmatched[index] = None;
+                matched += 1;
+            }
+            None => unmatched_statement.push(entry),
+        }
+    }
+
+    let closing = statement.closing_balance.clone().map(|closing| {
+        let balance = postings
+            .iter()
+            .filter(|posting| posting.date <= closing.date)
+            .map(|posting| posting.amount)
+            .sum();
+
+        (closing, balance)
+    });
+
+    Reconciliation {
+        matched,
+        unmatched_statement,
+        unmatched_ledger: unmatched
+            .into_iter()
+            .flatten()
+            .filter(|posting| within(posting, 0))
+            .collect(),
+        closing,
+    }
+}
+
+impl<'a> Display for Reconciliation<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} statement lines matched", self.matched)?;
+
+        if !self.unmatched_statement.is_empty() {
+            writeln!(
+                f,
+                "\n{} {} statement lines not found in the ledger:",
+                "warning:".yellow().bold(),
+                self.unmatched_statement.len()
+            )?;
+
+            for entry in &self.unmatched_statement {
+                writeln!(f, "  {}  {:>12}  {}", entry.date, entry.amount, entry.text)?;
+            }
+        }
+
+        if !self.unmatched_ledger.is_empty() {
+            writeln!(
+                f,
+                "\n{} {} postings not found on the statement:",
+                "warning:".yellow().bold(),
+                self.unmatched_ledger.len()
+            )?;
+
+            for posting in &self.unmatched_ledger {
+                writeln!(
+                    f,
+                    "  {}  {:>12}  {}  {}:{}",
+                    posting.date,
+                    posting.amount,
+                    posting.payee,
+                    posting.location.ledger().filename().to_string_lossy(),
+                    posting.location.start() + 1
+                )?;
+            }
+        }
+
+        if let Some((closing, balance)) = &self.closing {
+            let difference = closing.amount - balance;
+            let difference = if difference.is_zero() {
+                difference.to_string().green()
+            } else {
+                difference.to_string().red().bold()
+            };
+
+            writeln!(
+                f,
+                "\nclosing balance on {}: statement {}, ledger {}, difference {}",
+                closing.date, closing.amount, balance, difference
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::reconcile;
+    use crate::{
+        import::{parse_account, ClosingBalance, Entry, Statement},
+        inline_ledger,
+    };
+
+    #[test]
+    fn test_reconcile() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-01 * "Opening" ""
+            Assets:Bank:Account  10000 DKK
+            Equity:Opening
+
+        2000-01-03 * "Netto" ""
+            Assets:Bank:Account  -150 DKK
+            Expenses:Food
+
+        2000-01-05 * "DSB" ""
+            Assets:Bank:Account  -400 DKK
+            Expenses:Travel
+        "#
+        );
+
+        let date = |day| NaiveDate::from_ymd_opt(2000, 1, day).unwrap();
+        let entry = |day, amount, text: &str| Entry {
+            date: date(day),
+            text: text.to_string(),
+            amount: Decimal::new(amount, 0),
+            reference: None,
+        };
+
+        let statement = Statement {
+            currency: None,
+            entries: vec![entry(4, -150, "NETTO"), entry(6, -75, "Kiosk")],
+            closing_balance: Some(ClosingBalance {
+                date: date(6),
+                amount: Decimal::new(9775, 0),
+            }),
+        };
+
+        let directives = ledger.directives();
+        let account = parse_account("Assets:Bank:Account").unwrap();
+        let reconciliation = reconcile(&directives, &account, "DKK", &statement, 2);
+        println!("{}", reconciliation);
+
+        assert_eq!(reconciliation.matched, 1);
+        assert_eq!(reconciliation.unmatched_statement[0].text, "Kiosk");
+        assert_eq!(reconciliation.unmatched_ledger[0].payee, "DSB");
+
+        let (closing, balance) = reconciliation.closing.unwrap();
+        assert_eq!(closing.amount - balance, Decimal::new(-75 + 400, 0));
+        assert!(!reconciliation.is_reconciled());
+    }
+}
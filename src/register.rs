@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use beancount_core::{metadata::MetaValue, Directive};
+use chrono::NaiveDate;
+use regex::Regex;
+use rust_decimal::Decimal;
+use tabled::{
+    builder::Builder,
+    settings::{object::Columns, Alignment},
+    Table,
+};
+
+use crate::{
+    amount::resolved_postings, appendix::statement::FromStatementPath, date::naive_date,
+    ledger::Sourced, readable::account_name,
+};
+
+/// A posting to one of the registered accounts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line<'a> {
+    pub date: NaiveDate,
+    pub account: String,
+    pub payee: &'a str,
+    pub narration: &'a str,
+    pub amount: Decimal,
+    pub currency: Option<&'a str>,
+    /// Balance of the registered accounts in the currency of the posting,
+    /// including this posting.
+    pub balance: Decimal,
+    pub statement: Option<u64>,
+}
+
+/// Turn a glob such as `Expenses:*:Food` into a regex which matches those
+/// accounts, along with their subaccounts.
+pub fn account_glob(glob: &str) -> Regex {
+    let pattern: String = glob
+        .chars()
+        .map(|c| match c {
+            '*' => ".*".to_string(),
+            '?' => ".".to_string(),
+            c => regex::escape(&c.to_string()),
+        })
+        .collect();
+
+    Regex::new(&format!("^{}(:.*)?$", pattern)).expect("escaped glob is a valid regex")
+}
+
+/// Every posting to an account matching `accounts` in date order, with the
+/// running balance. Postings before `from` count towards the balance, but are
+/// not listed.
+pub fn register<'a>(
+    directives: &'a [Sourced<'a, Directive<'a>>],
+    accounts: &Regex,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Vec<Line<'a>> {
+    let mut transactions: Vec<_> = directives
+        .iter()
+        .filter_map(|directive| match &directive.inner {
+            Directive::Transaction(txn) => Some((naive_date(&txn.date)?, txn)),
+            _ => None,
+        })
+        .collect();
+    transactions.sort_by_key(|(date, _)| *date);
+
+    let mut balances = HashMap::<Option<&str>, Decimal>::new();
+    let mut lines = Vec::new();
+
+    for (date, txn) in transactions {
+        if to.is_some_and(|to| date > to) {
+            break;
+        }
+
+        let statement = match txn.meta.get("statement") {
+            Some(MetaValue::Text(statement)) => FromStatementPath::id(statement),
+            _ => None,
+        };
+
+        for posting in resolved_postings(txn) {
+            let account = account_name(posting.account);
+            if !accounts.is_match(&account) {
+                continue;
+            }
+
+            let balance = balances.entry(posting.currency).or_default();
+            *balance += posting.amount;
+
+            if from.is_some_and(|from| date < from) {
+                continue;
+            }
+
+            lines.push(Line {
+                date,
+                account,
+                payee: txn.payee.as_deref().unwrap_or_default(),
+                narration: txn.narration.as_ref(),
+                amount: posting.amount,
+                currency: posting.currency,
+                balance: *balance,
+                statement,
+            });
+        }
+    }
+
+    lines
+}
+
+pub fn table(lines: &[Line]) -> Table {
+    let mut builder = Builder::new();
+    builder.push_record([
+        "Date",
+        "Account",
+        "Payee",
+        "Narration",
+        "Amount",
+        "Balance",
+        "Statement",
+    ]);
+
+    for line in lines {
+        let currency = line.currency.unwrap_or_default();
+        builder.push_record([
+            line.date.to_string(),
+            line.account.clone(),
+            line.payee.to_string(),
+            line.narration.to_string(),
+            format!("{} {}", line.amount, currency),
+            format!("{} {}", line.balance, currency),
+            line.statement.map(|id| id.to_string()).unwrap_or_default(),
+        ]);
+    }
+
+    let mut table = builder.build();
+    table.modify(Columns::new(4..6), Alignment::right());
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::{account_glob, register};
+    use crate::inline_ledger;
+
+    #[test]
+    fn test_register() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-05 * "DSB" ""
+            statement: "documents/2000-01-05.2.dsb.pdf"
+            Assets:Bank:Account  -400 DKK
+            Expenses:Travel
+
+        2000-01-02 * "Netto" "Groceries"
+            statement: "documents/2000-01-02.1.netto.pdf"
+            Assets:Bank:Account  -150 DKK
+            Expenses:Food:Groceries
+
+        2000-02-01 * "Netto" ""
+            statement: "documents/2000-02-01.3.netto.pdf"
+            Assets:Bank:Account  -50 DKK
+            Expenses:Food
+        "#
+        );
+
+        let directives = ledger.directives();
+
+        let food = register(&directives, &account_glob("Expenses:Food"), None, None);
+        assert_eq!(food.len(), 2);
+        assert_eq!(food[0].account, "Expenses:Food:Groceries");
+        assert_eq!(food[0].narration, "Groceries");
+        assert_eq!(food[1].balance, Decimal::new(200, 0));
+        assert_eq!(food[1].statement, Some(3));
+
+        let bank = register(
+            &directives,
+            &account_glob("Assets:*"),
+            NaiveDate::from_ymd_opt(2000, 1, 3),
+            NaiveDate::from_ymd_opt(2000, 1, 31),
+        );
+        assert_eq!(bank.len(), 1);
+        assert_eq!(bank[0].payee, "DSB");
+        assert_eq!(bank[0].balance, Decimal::new(-550, 0));
+        println!("{}", super::table(&bank));
+
+        assert!(register(&directives, &account_glob("Expenses:Foo"), None, None).is_empty());
+    }
+}
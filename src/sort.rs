@@ -0,0 +1,174 @@
+use std::cmp::Reverse;
+
+use beancount_core::Directive;
+
+use crate::{
+    date::{directive_date, naive_date},
+    edit::FileEdit,
+    ledger::{Ledger, LedgerFile, Sourced},
+    lints::Chronology,
+};
+
+/// A directive along with the comments directly preceding it.
+struct Block {
+    date: chrono::NaiveDate,
+    /// Position of the block within the file before sorting.
+    position: usize,
+    /// Whether the block was preceded by a blank line.
+    separated: bool,
+    text: String,
+}
+
+fn sort_file(
+    file: &LedgerFile,
+    directives: &[&Sourced<Directive>],
+    chronology: Chronology,
+) -> String {
+    let lines: Vec<_> = file.original_contents().split_inclusive('\n').collect();
+
+    // Everything up to the first dated directive, such as options and includes,
+    // stays at the top of the file.
+    let mut prelude = String::new();
+    let mut blocks = Vec::new();
+    let mut previous_end = 0;
+    let mut previous_date = None;
+
+    for directive in directives {
+        let start = (directive.location.start() as usize).min(lines.len());
+        let end = (directive.location.end() as usize).min(lines.len());
+
+        // Undated directives stick to the directive preceding them.
+        let date = directive_date(&directive.inner)
+            .and_then(naive_date)
+            .or(previous_date);
+
+        match date {
+            None => prelude.extend(lines[previous_end..end].iter().copied()),
+            Some(date) => {
+                let gap = &lines[previous_end..start];
+                let blank_lines = gap
+                    .iter()
+                    .position(|line| !line.trim().is_empty())
+                    .unwrap_or(gap.len());
+
+                let mut text = lines[previous_end + blank_lines..end].concat();
+                if !text.ends_with('\n') {
+                    text.push('\n');
+                }
+
+                blocks.push(Block {
+                    date,
+                    position: blocks.len(),
+                    separated: blank_lines > 0,
+                    text,
+                });
+            }
+        }
+
+        previous_end = end;
+        previous_date = date;
+    }
+
+    match chronology {
+        Chronology::Ascending => blocks.sort_by_key(|block| block.date),
+        Chronology::Descending => blocks.sort_by_key(|block| Reverse(block.date)),
+    }
+
+    // Blocks keep their blank line, and those no longer following the same block
+    // as before get one, so moved blocks do not run into their neighbours.
+    let mut contents = prelude;
+    let mut next = 0;
+    for block in blocks {
+        if (block.separated || block.position != next) && !contents.is_empty() {
+            contents.push('\n');
+        }
+        contents.push_str(&block.text);
+        next = block.position + 1;
+    }
+    contents.extend(lines[previous_end..].iter().copied());
+
+    contents
+}
+
+/// Reorder the directives of each file by date, keeping comments directly
+/// preceding a directive attached to it.
+pub fn sort_ledger<'a>(
+    ledger: &'a Ledger,
+    directives: &[Sourced<'a, Directive<'a>>],
+    chronology: Chronology,
+) -> Vec<FileEdit<'a>> {
+    ledger
+        .files()
+        .map(|file| {
+            let mut directives: Vec<_> = directives
+                .iter()
+                .filter(|directive| directive.location.ledger() == file)
+                .collect();
+            directives.sort_by(|a, b| a.location.cmp(&b.location));
+
+            FileEdit {
+                ledger: file,
+                contents: sort_file(file, &directives, chronology),
+            }
+        })
+        .filter(|edit| !edit.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sort_ledger;
+    use crate::{inline_ledger, lints::Chronology};
+
+    #[test]
+    fn test_sort() {
+        let ledger = inline_ledger!(
+            r#"
+        option "title" "Example"
+
+        ; Paid by card
+        2000-01-03 * "Second Payee" ""
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Office
+
+        2000-01-01 open Assets:Bank:Account
+        2000-01-01 open Expenses:Office
+
+        2000-01-02 * "First Payee" ""
+            Assets:Bank:Account  -200 DKK
+            Expenses:Office
+        "#
+        );
+
+        let edits = sort_ledger(&ledger, &ledger.directives(), Chronology::Ascending);
+        assert_eq!(edits.len(), 1);
+
+        let contents = &edits[0].contents;
+
+        let position = |needle: &str| contents.find(needle).unwrap();
+        assert!(position("option") < position("open Assets"));
+        assert!(position("open Expenses") < position("First Payee"));
+        assert!(position("First Payee") < position("; Paid by card"));
+        assert!(position("; Paid by card") < position("Second Payee"));
+    }
+
+    #[test]
+    fn test_sort_separates_moved_blocks() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-02 * "Second Payee" ""
+            Assets:Bank:Account  -1500 DKK
+            Expenses:Office
+        2000-01-01 * "First Payee" ""
+            Assets:Bank:Account  -200 DKK
+            Expenses:Office
+        "#
+        );
+
+        let edits = sort_ledger(&ledger, &ledger.directives(), Chronology::Ascending);
+        assert_eq!(edits.len(), 1);
+        assert!(edits[0]
+            .contents
+            .contains("  Expenses:Office\n\n2000-01-02 * \"Second Payee\""));
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Range,
+};
+
+use beancount_core::Directive;
+use chrono::{Datelike, Months, NaiveDate};
+use clap::ValueEnum;
+use rust_decimal::Decimal;
+use tabled::{
+    builder::Builder,
+    settings::{object::Columns, Alignment},
+    Table,
+};
+
+use crate::{
+    balance::itemized_transactions, date::naive_date, error::Error, ledger::Sourced,
+    readable::account_name,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Period {
+    #[default]
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Period {
+    /// Label of the period containing `date`, such as `2023-01`, `2023-Q1` or `2023`.
+    pub fn label(&self, date: NaiveDate) -> String {
+        match self {
+            Period::Month => format!("{}-{:02}", date.year(), date.month()),
+            Period::Quarter => format!("{}-Q{}", date.year(), date.month0() / 3 + 1),
+            Period::Year => date.year().to_string(),
+        }
+    }
+
+    /// Dates within the period containing `date`.
+    pub fn range(&self, date: NaiveDate) -> Range<NaiveDate> {
+        let (first_month, months) = match self {
+            Period::Month => (date.month(), 1),
+            Period::Quarter => (date.month0() / 3 * 3 + 1, 3),
+            Period::Year => (1, 12),
+        };
+
+        let start = NaiveDate::from_ymd_opt(date.year(), first_month, 1)
+            .expect("periods start on a valid date");
+        let end = start
+            .checked_add_months(Months::new(months))
+            .expect("periods end on a valid date");
+
+        start..end
+    }
+}
+
+/// Sums of the postings to each account within each period.
+#[derive(Debug, Default)]
+pub struct Trend {
+    pub periods: BTreeSet<String>,
+    pub accounts: BTreeMap<String, BTreeMap<String, Decimal>>,
+}
+
+/// Whether `account` is `subtree` itself, or one of its subaccounts.
+pub(crate) fn within(account: &str, subtree: &str) -> bool {
+    account
+        .strip_prefix(subtree)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(':'))
+}
+
+/// Bucket the postings into periods, optionally only those within the
+/// `subtree` account, with accounts deeper than `depth` summed into their parent.
+pub fn trend(
+    directives: &[Sourced<Directive>],
+    period: Period,
+    subtree: Option<&str>,
+    depth: Option<usize>,
+) -> Result<Trend, Error> {
+    let mut trend = Trend::default();
+
+    for item in itemized_transactions(directives)? {
+        let Some(date) = naive_date(&item.date) else {
+            continue;
+        };
+        let label = period.label(date);
+
+        for posting in item.postings {
+            let account = account_name(&posting.account);
+            if subtree.is_some_and(|subtree| !within(&account, subtree)) {
+                continue;
+            }
+
+            let account = match depth {
+                Some(depth) => account.split(':').take(depth).collect::<Vec<_>>().join(":"),
+                None => account,
+            };
+
+            *trend
+                .accounts
+                .entry(account)
+                .or_default()
+                .entry(label.clone())
+                .or_default() += posting.amount;
+            trend.periods.insert(label.clone());
+        }
+    }
+
+    Ok(trend)
+}
+
+impl Trend {
+    fn total(&self, period: &str) -> Decimal {
+        self.accounts
+            .values()
+            .filter_map(|sums| sums.get(period))
+            .sum()
+    }
+
+    /// Rows of the matrix, headed by the periods, with the totals of each
+    /// account in the last column and of each period in the last row.
+    fn records(&self) -> Vec<Vec<String>> {
+        let mut header = vec!["Account".to_string()];
+        header.extend(self.periods.iter().cloned());
+        header.push("Total".to_string());
+
+        let mut records = vec![header];
+
+        for (account, sums) in &self.accounts {
+            let mut record = vec![account.clone()];
+            record.extend(
+                self.periods
+                    .iter()
+                    .map(|period| sums.get(period).copied().unwrap_or_default().to_string()),
+            );
+            record.push(sums.values().sum::<Decimal>().to_string());
+            records.push(record);
+        }
+
+        let mut totals = vec!["Total".to_string()];
+        totals.extend(
+            self.periods
+                .iter()
+                .map(|period| self.total(period).to_string()),
+        );
+        totals.push(
+            self.accounts
+                .values()
+                .flat_map(|sums| sums.values())
+                .sum::<Decimal>()
+                .to_string(),
+        );
+        records.push(totals);
+
+        records
+    }
+
+    pub fn table(&self) -> Table {
+        let mut builder = Builder::new();
+        for record in self.records() {
+            builder.push_record(record);
+        }
+
+        let mut table = builder.build();
+        table.modify(Columns::new(1..), Alignment::right());
+        table
+    }
+
+    pub fn csv(&self) -> Result<String, csv::Error> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for record in self.records() {
+            writer.write_record(record)?;
+        }
+
+        let bytes = writer.into_inner().map_err(|err| err.into_error())?;
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::{trend, Period};
+    use crate::{error::Error, inline_ledger};
+
+    #[test]
+    fn test_trend() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-02 * "Netto" ""
+            statement: "documents/2000-01-02.1.netto.pdf"
+            Assets:Bank:Account  -150 DKK
+            Expenses:Food:Groceries
+
+        2000-01-05 * "DSB" ""
+            statement: "documents/2000-01-05.2.dsb.pdf"
+            Assets:Bank:Account  -400 DKK
+            Expenses:Travel
+
+        2000-01-10 * "Netto" ""
+            Assets:Bank:Account  -100 DKK
+            Expenses:Food:Groceries
+
+        2000-04-01 * "Restaurant" ""
+            statement: "documents/2000-04-01.3.restaurant.pdf"
+            Assets:Bank:Account  -50 DKK
+            Expenses:Food:Restaurants
+        "#
+        );
+
+        let directives = ledger.directives();
+
+        let quarterly = trend(&directives, Period::Quarter, Some("Expenses"), Some(2)).unwrap();
+        assert_eq!(quarterly.periods.len(), 2);
+        assert_eq!(quarterly.accounts.len(), 2);
+        assert_eq!(
+            quarterly.accounts["Expenses:Food"]["2000-Q1"],
+            Decimal::new(250, 0)
+        );
+        assert_eq!(
+            quarterly.accounts["Expenses:Food"]["2000-Q2"],
+            Decimal::new(50, 0)
+        );
+        assert_eq!(quarterly.total("2000-Q1"), Decimal::new(650, 0));
+
+        let csv = quarterly.csv().unwrap();
+        assert_eq!(csv.lines().next(), Some("Account,2000-Q1,2000-Q2,Total"));
+        assert_eq!(csv.lines().last(), Some("Total,650,50,700"));
+        println!("{}", quarterly.table());
+
+        let monthly = trend(&directives, Period::Month, None, None).unwrap();
+        assert_eq!(monthly.accounts.len(), 4);
+        assert_eq!(monthly.total("2000-01"), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_trend_missing_price() {
+        let ledger = inline_ledger!(
+            r#"
+        2000-01-02 * "Amazon" ""
+            Assets:Bank:Account  -15 USD
+            Expenses:Books
+        "#
+        );
+
+        let err = trend(&ledger.directives(), Period::Month, None, None).unwrap_err();
+        assert!(matches!(err, Error::MissingPrice(_)));
+    }
+}
@@ -0,0 +1,263 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::mpsc::channel,
+    time::Duration,
+};
+
+use colored::Colorize;
+use log::{debug, warn};
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode};
+
+use crate::{
+    appendix::hashes::{DocumentHashes, HASHES_FILE},
+    error::Error,
+    ledger::{Ledger, LedgerFile},
+    lints::{self, Lint, LintOptions},
+};
+
+/// Identity of a finding which, unlike its rendering, does not change when
+/// lines are added or removed elsewhere in the ledger.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Identity {
+    kind: &'static str,
+    /// File and text of each directive the finding refers to.
+    sources: Vec<(PathBuf, String)>,
+    /// Number of other findings with the same kind and sources before this one.
+    occurrence: usize,
+}
+
+impl Identity {
+    fn of(lint: &Lint) -> Self {
+        Identity {
+            kind: lint.kind(),
+            sources: lint
+                .locations()
+                .iter()
+                .map(|location| {
+                    let text: Vec<_> = location.lines().collect();
+                    (location.ledger().filename(), text.join("\n"))
+                })
+                .collect(),
+            occurrence: 0,
+        }
+    }
+}
+
+/// Findings which appeared or disappeared since the previous run.
+#[derive(Debug, Default)]
+pub struct Changes {
+    pub new: Vec<String>,
+    pub resolved: Vec<String>,
+}
+
+/// State kept between runs of the lints, so only changed files have to be re-parsed.
+pub struct Session {
+    root: PathBuf,
+    options: LintOptions,
+    hashes: DocumentHashes,
+    ledger: Ledger,
+    /// Findings of the previous run, along with their rendering.
+    findings: BTreeMap<Identity, String>,
+}
+
+fn is_ledger_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "beancount")
+}
+
+impl Session {
+    /// Load the ledger in `root`. The path is canonicalized, so the paths of
+    /// the files match those reported by the file watcher.
+    pub fn new(root: &Path, options: LintOptions) -> Result<Self, Error> {
+        let root = root.canonicalize()?;
+
+        Ok(Session {
+            options,
+            hashes: DocumentHashes::load(root.join(HASHES_FILE)),
+            ledger: Ledger::from_path(&root)?,
+            findings: BTreeMap::new(),
+            root,
+        })
+    }
+
+    /// Re-read the changed files, forgetting those which no longer exist.
+    pub fn update(&mut self, changed: impl IntoIterator<Item = PathBuf>) {
+        for path in changed.into_iter().filter(|path| is_ledger_file(path)) {
+            debug!("reloading {}", path.to_string_lossy());
+
+            if path.exists() {
+                match LedgerFile::from_file(path.clone()) {
+                    Ok(file) => self.ledger.insert(file),
+                    Err(err) => warn!("failed to read {}: {}", path.to_string_lossy(), err),
+                }
+            } else {
+                self.ledger.remove(&path);
+            }
+        }
+    }
+
+    /// Run the lints against the current state of the ledger, returning the
+    /// findings which changed since the previous run.
+    pub fn run(&mut self) -> Changes {
+        let mut findings = BTreeMap::new();
+        let mut files = Vec::new();
+        let mut directives = Vec::new();
+
+        for (file, parsed) in self.ledger.parsed_files() {
+            match parsed {
+                Ok(parsed) => {
+                    files.push(file);
+                    directives.extend(parsed.iter().cloned());
+                }
+                Err(err) => {
+                    let identity = Identity {
+                        kind: "error",
+                        sources: vec![(file.filename(), err.to_string())],
+                        occurrence: 0,
+                    };
+                    findings.insert(identity, format!("{} {}\n", "error:".red().bold(), err));
+                }
+            }
+        }
+
+        for lint in lints::run(&files, &directives, &mut self.hashes, &self.options) {
+            let mut identity = Identity::of(&lint);
+            while findings.contains_key(&identity) {
+                identity.occurrence += 1;
+            }
+
+            findings.insert(identity, lint.to_string());
+        }
+
+        if let Err(err) = self.hashes.save(self.root.join(HASHES_FILE)) {
+            warn!("failed to save document hash cache: {}", err);
+        }
+
+        let difference =
+            |a: &BTreeMap<Identity, String>, b: &BTreeMap<Identity, String>| -> Vec<String> {
+                a.iter()
+                    .filter(|(identity, _)| !b.contains_key(identity))
+                    .map(|(_, finding)| finding.clone())
+                    .collect()
+            };
+
+        let changes = Changes {
+            new: difference(&findings, &self.findings),
+            resolved: difference(&self.findings, &findings),
+        };
+
+        self.findings = findings;
+        changes
+    }
+
+    pub fn findings(&self) -> usize {
+        self.findings.len()
+    }
+}
+
+fn report(changes: &Changes, remaining: usize) {
+    for finding in &changes.resolved {
+        eprint!("{} {}", "resolved:".green().bold(), finding);
+    }
+
+    for finding in &changes.new {
+        eprint!("{}", finding);
+    }
+
+    eprintln!(
+        "{} new, {} resolved, {} remaining",
+        changes.new.len(),
+        changes.resolved.len(),
+        remaining
+    );
+}
+
+/// Watch the ledger directory, re-running the lints whenever files change.
+/// Changes arriving within `debounce` of each other are handled as a single run.
+pub fn watch(root: &Path, options: LintOptions, debounce: Duration) -> Result<(), Error> {
+    let mut session = Session::new(root, options)?;
+    report(&session.run(), session.findings());
+
+    let (sender, receiver) = channel();
+    let mut debouncer = new_debouncer(debounce, sender)?;
+    debouncer
+        .watcher()
+        .watch(&session.root, RecursiveMode::Recursive)?;
+
+    eprintln!("watching {} for changes", session.root.to_string_lossy());
+
+    for events in receiver {
+        match events {
+            Ok(events) => {
+                let changed: BTreeSet<_> = events.into_iter().map(|event| event.path).collect();
+                if !changed.iter().any(|path| is_ledger_file(path)) {
+                    continue;
+                }
+
+                session.update(changed);
+                report(&session.run(), session.findings());
+            }
+            Err(err) => warn!("watch error: {}", err),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::Session;
+    use crate::lints::LintOptions;
+
+    #[test]
+    fn test_watch_session() {
+        let root = std::path::PathBuf::from("target/test-watch");
+        std::fs::remove_dir_all(&root).ok();
+        std::fs::create_dir_all(&root).unwrap();
+
+        let ledger = root.join("ledger.beancount");
+        let transaction = "2000-01-01 * \"Example Payee\" \"\"\n  Assets:Bank:Account  -1500 DKK\n  Assets:Bank:Savings\n\n";
+        std::fs::write(&ledger, transaction).unwrap();
+
+        let options = LintOptions {
+            double_entry_window: 0,
+            near_duplicates: false,
+            near_duplicate_window: 1,
+            near_duplicate_similarity: 80,
+            verify_amounts: false,
+            chronological: None,
+            over_budget: false,
+            today: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+        };
+
+        let mut session = Session::new(&root, options).unwrap();
+        let initial = session.run();
+        assert!(initial.resolved.is_empty());
+
+        std::fs::write(&ledger, transaction.repeat(2)).unwrap();
+        session.update([ledger.canonicalize().unwrap()]);
+        let duplicated = session.run();
+        assert!(duplicated
+            .new
+            .iter()
+            .any(|finding| finding.contains("identical transaction")));
+
+        // Moving the findings around does not make them new.
+        std::fs::write(&ledger, format!("\n\n{}", transaction.repeat(2))).unwrap();
+        session.update([ledger.canonicalize().unwrap()]);
+        let moved = session.run();
+        assert!(moved.new.is_empty());
+        assert!(moved.resolved.is_empty());
+
+        std::fs::write(&ledger, transaction).unwrap();
+        session.update([ledger.canonicalize().unwrap()]);
+        let fixed = session.run();
+        assert!(fixed
+            .resolved
+            .iter()
+            .any(|finding| finding.contains("identical transaction")));
+    }
+}